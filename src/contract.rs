@@ -1,41 +1,82 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{Binary,to_binary, WasmMsg, Coin, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Addr, BankMsg, CosmosMsg, StdError , Order};
+use cosmwasm_std::{Binary,to_json_binary, WasmMsg, Coin, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult, Addr, BankMsg, CosmosMsg, StdError , Order, Storage};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, OfferResp, ContractConfig, NFTCollectionResp, offer_resps };
-use crate::state::{ LEND_DENOM, NFT_COLLECTIONS, LAST_OFFER_INDEX, CONFIG };
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, OfferResp, OfferListResp, OffersByPriceResp, PriceCursor, DueCursor, LoansDueResp, OfferStatus, OrderBookDepthResp, DepthLevel, RepayQuoteResp, PositionStatusResp, SimulateBorrowResp, SimulateLendResp, ArchivedOfferResp, HistoryResp, UserStatsResp, archived_offers, ContractConfig, ConfigResp, NFTCollectionMsg, NFTCollectionResp, NFTCollectionListResp, CollectionSummaryResp, LoanStats, TraitFilter, Collateral, RepayRecipient, LoanTerms, Role, Operation, PauseSettings, PauseStateResp, CollectionPauseResp, offer_resps };
+use crate::state::{ LEND_DENOM, NFT_COLLECTIONS, LAST_COLLECTION_ID, COLLECTION_BY_CONTRACT, STATS, COLLECTION_STATS, LENDER_STATS, BORROWER_STATS, COLLECTION_LOANS, LAST_OFFER_INDEX, CONFIG, TOKEN_APPRAISALS, TRAIT_APPRAISALS, NOTE_CONTRACT, RECEIPT_CONTRACT, PENDING_ADMIN, ROLES, ACCRUED_FEES, PAUSED_OPERATIONS, PAUSED_COLLECTION_OPERATIONS, PAUSE_SETTINGS };
+use crate::helpers::{ appraise, query_nft_contract_info, query_nft_owner, query_nft_traits };
+use crate::upgrades::{ run_upgrades, FIRST_RELEASE };
+use cw2::{ ContractVersion, set_contract_version };
+use semver::Version;
 use cw721::Cw721ExecuteMsg;
+use cw_storage_plus::Bound;
 
-/*
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:foxy-lend";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-*/
+// Upper bound on the NFTs pledged for a single loan
+const MAX_BUNDLE_SIZE: usize = 10;
+// Lender share of the interest is a percentage
+const MAX_INTEREST: u128 = 100;
+// Page sizes of list queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let nft_collections = msg.nft_collections;
 
-    for collection_resp in nft_collections {
-        NFT_COLLECTIONS.save(deps.storage, collection_resp.collection_id, &collection_resp)?;
+    LAST_COLLECTION_ID.save(deps.storage, &0)?;
+    for collection in nft_collections {
+        exec::register_collection(deps.branch(), collection)?;
     }
 
-    // NFT_COLLECTIONS.save(deps.storage, &nft_collections)?;
-
+    if msg.interest > MAX_INTEREST {
+        return Err(ContractError::InvalidInterest);
+    }
     let config = ContractConfig { admin: msg.admin, interest: msg.interest };
     CONFIG.save(deps.storage, &config)?;
 
     LEND_DENOM.save(deps.storage, &"SEI".to_string())?;
     LAST_OFFER_INDEX.save(deps.storage, &0)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new())
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = match cw2::CONTRACT.may_load(deps.storage)? {
+        Some(stored) => stored,
+        None => ContractVersion { contract: CONTRACT_NAME.to_string(), version: FIRST_RELEASE.to_string() },
+    };
+
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::WrongContract { contract: stored.contract });
+    }
+
+    let from: Version = stored.version.parse()?;
+    let to: Version = CONTRACT_VERSION.parse()?;
+    if from > to {
+        return Err(ContractError::CannotDowngrade { from: from.to_string(), to: to.to_string() });
+    }
+
+    let upgrades = run_upgrades(deps.storage, &from, &to)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", from.to_string())
+        .add_attribute("to_version", to.to_string())
+        .add_attributes(upgrades.into_iter().map(|version| ("upgrade", version))))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -46,12 +87,16 @@ pub fn execute(
     use ExecuteMsg::*;
 
     match msg {
-        Lend { amount, collection_id } => exec::lend(
+        Lend { amount, collection_id, token_id, trait_filter, bundle, allowed_borrowers } => exec::lend(
             deps, 
             env,
             info, 
             amount,
-            collection_id
+            collection_id,
+            token_id,
+            trait_filter,
+            bundle,
+            allowed_borrowers
         ),
         CancelOffer { offer_id } => exec::cancel_offer(
             deps,
@@ -59,14 +104,20 @@ pub fn execute(
             env,
             offer_id
         ),
-        Borrow { owner, offer_id, token_id} => exec::borrow (
+        Borrow { offer_id, token_id} => exec::borrow (
             deps,
             env,
             info,
-            owner,
             offer_id,
             token_id
         ),
+        BorrowBundle { offer_id, collateral } => exec::borrow_bundle(
+            deps,
+            env,
+            info,
+            offer_id,
+            collateral
+        ),
         UpdateFloorPrice{ collection_id, new_floor_price } => exec::update_floor_price (
             deps,
             info,
@@ -78,56 +129,180 @@ pub fn execute(
             info,
             collection
         ),
-        UpdateAdmin { new_admin } => exec::update_admin (
+        UpdateCollection { collection_id, collection, contract, apy, max_time, risk_tier, disabled } => exec::update_collection(
+            deps,
+            info,
+            collection_id,
+            collection,
+            contract,
+            apy,
+            max_time,
+            risk_tier,
+            disabled
+        ),
+        RemoveCollection { collection_id } => exec::remove_collection(
+            deps,
+            info,
+            env,
+            collection_id
+        ),
+        ProposeAdmin { new_admin } => exec::propose_admin(
             deps,
             info,
             new_admin
         ),
+        AcceptAdmin {} => exec::accept_admin(
+            deps,
+            info
+        ),
+        GrantRole { role, address } => exec::grant_role(
+            deps,
+            info,
+            role,
+            address
+        ),
+        RevokeRole { role, address } => exec::revoke_role(
+            deps,
+            info,
+            role,
+            address
+        ),
+        WithdrawFees { amount, recipient } => exec::withdraw_fees(
+            deps,
+            info,
+            amount,
+            recipient
+        ),
+        Pause { operations, collection_id } => exec::set_paused(
+            deps,
+            info,
+            operations,
+            collection_id,
+            true
+        ),
+        Unpause { operations, collection_id } => exec::set_paused(
+            deps,
+            info,
+            operations,
+            collection_id,
+            false
+        ),
+        UpdatePauseSettings { repay_open_during_pause, cancel_open_during_pause } => exec::update_pause_settings(
+            deps,
+            info,
+            repay_open_during_pause,
+            cancel_open_during_pause
+        ),
         UpdateInterest { interest } => exec::update_interest(
             deps,
             info,
             interest
         ),
-        Repay { owner,offer_id } => exec::repay (
+        Repay { offer_id, max_payment } => exec::repay (
+            deps,
+            info,
+            env,
+            offer_id,
+            max_payment
+        ),
+        Liquidate { offer_id } => exec::liquidate(
             deps,
             info,
             env,
-            owner,
             offer_id
-        )
+        ),
+        RepayFor { offer_id, recipient, max_payment } => exec::repay_for(
+            deps,
+            info,
+            env,
+            offer_id,
+            recipient,
+            max_payment
+        ),
+        AuthorizeRepayRecipient { offer_id, recipient } => exec::authorize_repay_recipient(
+            deps,
+            info,
+            offer_id,
+            recipient
+        ),
+        SetTokenAppraisal { collection_id, token_id, multiplier } => exec::set_token_appraisal(
+            deps,
+            info,
+            collection_id,
+            token_id,
+            multiplier
+        ),
+        SetTraitAppraisal { collection_id, trait_type, value, multiplier } => exec::set_trait_appraisal(
+            deps,
+            info,
+            collection_id,
+            trait_type,
+            value,
+            multiplier
+        ),
+        SetNoteContract { note_contract } => exec::set_note_contract(
+            deps,
+            env,
+            info,
+            note_contract
+        ),
+        SetReceiptContract { receipt_contract } => exec::set_receipt_contract(
+            deps,
+            env,
+            info,
+            receipt_contract
+        ),
     }
 }
 
 mod exec {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn lend(
         deps: DepsMut, 
         env: Env,
         info: MessageInfo,
         amount: u128,
         collection_id: u16,
+        token_id: Option<String>,
+        trait_filter: Option<Vec<TraitFilter>>,
+        bundle: bool,
+        allowed_borrowers: Option<Vec<Addr>>,
     ) -> Result<Response, ContractError> {
+        let max_amount = plan_lend(deps.as_ref(), collection_id, token_id.as_deref(), bundle)?;
+
         let denom = LEND_DENOM.load(deps.storage)?;
         let offer_index = LAST_OFFER_INDEX.load(deps.storage)?; 
-        // Get the collection associated with the offer
-        let collection = match NFT_COLLECTIONS.may_load(deps.storage, collection_id)? {
-            Some(collection) => collection,
-            None => return Err(ContractError::CollectionNotFound),
-        };
-
-      
         let start_time = env.block.time.seconds();
 
+        let allowed_borrowers = allowed_borrowers
+            .map(|borrowers| {
+                borrowers
+                    .iter()
+                    .map(|borrower| deps.api.addr_validate(borrower.as_str()))
+                    .collect::<StdResult<Vec<_>>>()
+            })
+            .transpose()?;
+
         let offer = OfferResp {
             offer_id: offer_index + 1,
             owner: info.sender.clone(),
             amount,
             start_time,
             collection_id,
-            token_id: "".to_string(), // Adjust the type according to your token identifier type
-            accepted: false,
-            borrower: Addr::unchecked("none"),
+            token_id: token_id.unwrap_or_default(), // empty for offers on any token of the collection
+            status: OfferStatus::Open,
+            borrower: None,
+            // An empty filter would accept any token, same as no filter
+            trait_filter: trait_filter.filter(|filter| !filter.is_empty()),
+            bundle,
+            collateral: vec![],
+            allowed_borrowers,
+            note_contract: None,
+            receipt_contract: None,
+            repay_recipient: None,
+            loan_terms: None,
         };
 
         match cw_utils::must_pay(&info, &denom) {
@@ -135,7 +310,7 @@ mod exec {
                 if payment.u128() != amount {
                     return Err(ContractError::NotExactAmount);
                 }
-                if collection.floor_price < payment.u128() {
+                if max_amount.is_some_and(|max_amount| max_amount < payment.u128()) {
                     return Err(ContractError::TooMuchLendAmount)
                 }
             },
@@ -143,8 +318,12 @@ mod exec {
         };
        
         // Save the offer and update the last offer index
-        offer_resps().save(deps.storage,(&offer.owner, offer.offer_id), &offer)?;
+        offer_resps().save(deps.storage, offer.offer_id, &offer)?;
         LAST_OFFER_INDEX.save(deps.storage, &(offer_index + 1))?;
+        update_stats(deps.storage, &offer, |stats| {
+            stats.open_offers += 1;
+            stats.tvl += amount;
+        })?;
         // Return the BankMsg::Send message as a response
         Ok(Response::new()
             .add_attribute("action", "lend"))
@@ -154,28 +333,25 @@ mod exec {
         deps: DepsMut,
         info: MessageInfo,
         env: Env,
-        offer_id: u16
+        offer_id: u64
     ) -> Result<Response, ContractError> {
         // Load the denom
         let denom = LEND_DENOM.load(deps.storage)?;
-        let config = CONFIG.load(deps.storage)?;
         let owner = info.sender;
 
         // Load the offer from storage
-        let Some(offer) = offer_resps().may_load(deps.storage, (&owner, offer_id))? else {
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
             return Err(ContractError::OfferNotFound); // Return error if offer does not exist
         };
 
-        // Check if the sender is the owner of the offer
-        if offer.owner != owner.clone() {
-            if config.admin != owner.clone() {
-                return Err(ContractError::InvalidOfferOwner);
-            }
+        // Check if the sender is the owner of the offer, pausers may cancel on their behalf
+        if offer.owner != owner && !has_role(deps.as_ref(), &owner, Role::Pauser)? {
+            return Err(ContractError::InvalidOfferOwner);
         }
 
-        if offer.accepted {
-            return Err(ContractError::OfferAlreadyAccepted);
-        }
+        assert_not_paused(deps.as_ref(), Operation::Cancel, &[offer.collection_id])?;
+
+        let cancelled = transition(&offer, OfferStatus::Cancelled)?;
 
         // Repay the amount to the sender
         let message = BankMsg::Send {
@@ -186,8 +362,8 @@ mod exec {
             }],
         };
         
-        // Remove the offer from storage
-        offer_resps().remove(deps.storage, (&offer.owner,offer_id));
+        archive_offer(deps.storage, &env, cancelled, 0, 0)?;
+        withdraw_offer_stats(deps.storage, &offer)?;
 
         // Return a response with the repayment message
         Ok(Response::new()
@@ -200,40 +376,171 @@ mod exec {
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        owner: Addr,
-        offer_id: u16,
+        offer_id: u64,
         token_id: String,
     ) -> Result<Response, ContractError> {
-        let denom = LEND_DENOM.load(deps.storage)?;
         // Load the offer from storage
-        let Some(offer) = offer_resps().may_load(deps.storage, (&owner, offer_id))? else {
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
             return Err(ContractError::OfferNotFound); // Return error if offer does not exist
         };
 
-        let contract_address = env.contract.address.clone();
+        let collateral = vec![Collateral { collection_id: offer.collection_id, token_id }];
+        accept_offer(deps, env, info, offer, collateral)
+    }
+
+    pub fn borrow_bundle(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        offer_id: u64,
+        collateral: Vec<Collateral>,
+    ) -> Result<Response, ContractError> {
+        // Load the offer from storage
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
+            return Err(ContractError::OfferNotFound); // Return error if offer does not exist
+        };
 
-        // Check if the offer is not already accepted
-        if offer.accepted {
-            return Err(ContractError::OfferAlreadyAccepted);
+        if !offer.bundle {
+            return Err(ContractError::NotBundleOffer);
         }
+        check_bundle(&collateral)?;
+
+        accept_offer(deps, env, info, offer, collateral)
+    }
 
-        // Get the collection associated with the offer
-        let collection = match NFT_COLLECTIONS.may_load(deps.storage, offer.collection_id)? {
+    // Checks a new offer can be made on the collection, returns the most it may lend,
+    // none when uncapped. Shared with `SimulateLend`.
+    pub fn plan_lend(deps: Deps, collection_id: u16, token_id: Option<&str>, bundle: bool) -> Result<Option<u128>, ContractError> {
+        assert_not_paused(deps, Operation::Lend, &[collection_id])?;
+
+        let collection = match NFT_COLLECTIONS.may_load(deps.storage, collection_id)? {
             Some(collection) => collection,
             None => return Err(ContractError::CollectionNotFound),
         };
-        
-        // Send the NFT to the contract address
-        let msg = Cw721ExecuteMsg::TransferNft {
-            recipient: contract_address.to_string(),
-            token_id: token_id.to_string(),
+
+        if collection.disabled {
+            return Err(ContractError::CollectionDisabled);
+        }
+
+        // Token-specific offers are capped by the appraisal of that token instead of the floor,
+        // bundle offers only by the appraisal of the whole bundle on borrow
+        let max_amount = match token_id {
+            Some(token_id) => Some(appraise(deps, &collection, token_id)?.value),
+            None if bundle => None,
+            None => Some(collection.floor_price),
         };
+        Ok(max_amount)
+    }
 
-        let execute_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: collection.contract.to_string(),
-            msg: to_binary(&msg)?,
-            funds: vec![],
-        });
+    // A checked borrow: the offer as it becomes a loan and the value backing it
+    pub struct BorrowPlan {
+        pub accepted: OfferResp,
+        pub loan_terms: LoanTerms,
+        pub appraised_value: u128,
+    }
+
+    // Runs every check of a borrow without moving anything, shared with `SimulateBorrow`
+    pub fn plan_borrow(deps: Deps, sender: &Addr, offer: &OfferResp, collateral: &[Collateral]) -> Result<BorrowPlan, ContractError> {
+        let accepted = transition(offer, OfferStatus::Active)?;
+
+        let mut collection_ids: Vec<u16> = collateral.iter().map(|item| item.collection_id).collect();
+        collection_ids.push(offer.collection_id);
+        assert_not_paused(deps, Operation::Borrow, &collection_ids)?;
+
+        // The loan runs under the current terms of the offer collection
+        let offer_collection = match NFT_COLLECTIONS.may_load(deps.storage, offer.collection_id)? {
+            Some(collection) => collection,
+            None => return Err(ContractError::CollectionNotFound),
+        };
+        if offer_collection.disabled {
+            return Err(ContractError::CollectionDisabled);
+        }
+        let loan_terms = LoanTerms { apy: offer_collection.apy, max_time: offer_collection.max_time };
+
+        // Private offers are reserved for the borrowers picked by the lender
+        if let Some(allowed_borrowers) = &offer.allowed_borrowers {
+            if !allowed_borrowers.contains(sender) {
+                return Err(ContractError::InvalidBorrow);
+            }
+        }
+
+        // Plain offers take a single NFT of their own collection
+        if !offer.bundle && (collateral.len() != 1 || collateral[0].collection_id != offer.collection_id) {
+            return Err(ContractError::NotBundleOffer);
+        }
+
+        // Token-specific offers can only be accepted with that token
+        let reserved = Collateral { collection_id: offer.collection_id, token_id: offer.token_id.clone() };
+        if !offer.token_id.is_empty() && collateral != [reserved] {
+            return Err(ContractError::TokenMismatch);
+        }
+
+        let mut appraised_value: u128 = 0;
+        for item in collateral {
+            // Get the collection associated with the item
+            let collection = match NFT_COLLECTIONS.may_load(deps.storage, item.collection_id)? {
+                Some(collection) => collection,
+                None => return Err(ContractError::CollectionNotFound),
+            };
+
+            if collection.disabled {
+                return Err(ContractError::CollectionDisabled);
+            }
+
+            // Only the holder of the NFT may pledge it
+            if query_nft_owner(deps, &collection.contract, &item.token_id)? != *sender {
+                return Err(ContractError::InvalidNftOwner);
+            }
+
+            // Trait-filtered offers only accept NFTs carrying every requested trait
+            if let Some(trait_filter) = &offer.trait_filter {
+                let traits = query_nft_traits(deps, &collection.contract, &item.token_id);
+                let matches = trait_filter.iter().all(|filter| {
+                    traits.iter().any(|t| t.trait_type == filter.trait_type && t.value == filter.value)
+                });
+                if !matches {
+                    return Err(ContractError::TraitMismatch);
+                }
+            }
+
+            appraised_value += appraise(deps, &collection, &item.token_id)?.value;
+        }
+
+        // The loan may not be worth more than the appraised value of the collateral
+        if offer.amount > appraised_value {
+            return Err(ContractError::ExceedsAppraisedValue);
+        }
+
+        Ok(BorrowPlan { accepted, loan_terms, appraised_value })
+    }
+
+    // Bundles hold between one and `MAX_BUNDLE_SIZE` distinct NFTs
+    pub fn check_bundle(collateral: &[Collateral]) -> Result<(), ContractError> {
+        if collateral.is_empty() || collateral.len() > MAX_BUNDLE_SIZE {
+            return Err(ContractError::InvalidBundle);
+        }
+        // The same NFT can't be pledged twice
+        for (i, item) in collateral.iter().enumerate() {
+            if collateral[..i].contains(item) {
+                return Err(ContractError::InvalidBundle);
+            }
+        }
+        Ok(())
+    }
+
+    // Moves the collateral into escrow and pays the offer out to the borrower
+    fn accept_offer(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        offer: OfferResp,
+        collateral: Vec<Collateral>,
+    ) -> Result<Response, ContractError> {
+        let denom = LEND_DENOM.load(deps.storage)?;
+        let BorrowPlan { accepted, loan_terms, .. } = plan_borrow(deps.as_ref(), &info.sender, &offer, &collateral)?;
+
+        // Send the NFTs to the contract address
+        let mut messages = collateral_transfer_msgs(deps.as_ref(), &collateral, &env.contract.address)?;
 
         let fund_msg = BankMsg::Send {
             to_address: info.sender.clone().into_string(),
@@ -242,21 +549,49 @@ mod exec {
                 amount: offer.amount.into(),    // Payment amount
             }],
         };
-    
-        let messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(fund_msg), execute_msg];
-      
+        messages.insert(0, CosmosMsg::Bank(fund_msg));
+
+        // The lender position is represented by a transferable promissory note
+        let note_contract = NOTE_CONTRACT.may_load(deps.storage)?;
+        if let Some(note_contract) = &note_contract {
+            messages.push(mint_position_msg(note_contract, offer.offer_id, &offer.owner)?);
+        }
+
+        // and the borrower position by a receipt entitling its holder to repay
+        let receipt_contract = RECEIPT_CONTRACT.may_load(deps.storage)?;
+        if let Some(receipt_contract) = &receipt_contract {
+            messages.push(mint_position_msg(receipt_contract, offer.offer_id, &info.sender)?);
+        }
+
+        // Single-token loans keep reporting their token in `token_id`
+        let token_id = match collateral.as_slice() {
+            [item] => item.token_id.clone(),
+            _ => offer.token_id.clone(),
+        };
+
         // Save the updated offer back to storage
-        offer_resps().replace(
-            deps.storage,
-            (&offer.owner, offer.offer_id), 
-            Some(&OfferResp {
-                token_id: token_id,
-                accepted: true,
-                borrower: info.sender.clone(),
-                ..offer.clone()
-            }), 
-            Some(&offer)
-        )?;
+        let loan = OfferResp {
+            token_id,
+            borrower: Some(info.sender.clone()),
+            collateral,
+            note_contract,
+            receipt_contract,
+            loan_terms: Some(loan_terms),
+            ..accepted
+        };
+        offer_resps().replace(deps.storage, offer.offer_id, Some(&loan), Some(&offer))?;
+        count_collection_loans(deps.storage, &loan, true)?;
+        update_stats(deps.storage, &offer, |stats| {
+            stats.open_offers = stats.open_offers.saturating_sub(1);
+            stats.tvl = stats.tvl.saturating_sub(offer.amount);
+        })?;
+        let open_loan = |stats: &mut LoanStats| {
+            stats.active_loans += 1;
+            stats.outstanding_principal += offer.amount;
+            stats.volume += offer.amount;
+        };
+        update_stats(deps.storage, &offer, open_loan)?;
+        update_borrower_stats(deps.storage, &info.sender, open_loan)?;
         
         // Return success response
         Ok(Response::new()
@@ -270,11 +605,8 @@ mod exec {
         collection_id: u16,
         new_floor_price: u128
     ) -> Result<Response, ContractError> {
-        let config = CONFIG.load(deps.storage)?;
-
-        if config.admin != info.sender {
-            return Err(ContractError::Unauthorized);
-        }
+        assert_role(deps.as_ref(), &info.sender, Role::PriceUpdater)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[collection_id])?;
 
         let mut collection = match NFT_COLLECTIONS.may_load(deps.storage, collection_id)? {
             Some(collection) => collection,
@@ -294,322 +626,1643 @@ mod exec {
     pub fn add_nft_collection(
         deps: DepsMut,
         info: MessageInfo,
-        collection: NFTCollectionResp,
+        collection: NFTCollectionMsg,
     ) -> Result<Response, ContractError> {
-        let config = CONFIG.load(deps.storage)?;
+        assert_role(deps.as_ref(), &info.sender, Role::CollectionManager)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[])?;
 
-        if config.admin != info.sender {
-            return Err(ContractError::Unauthorized);
-        }
-
-        NFT_COLLECTIONS.save(deps.storage,collection.collection_id,  &collection);
+        let collection = register_collection(deps, collection)?;
 
         Ok(Response::new()
-                .add_attribute("action", "add_nft_collection"))
+                .add_attribute("action", "add_nft_collection")
+                .add_attribute("collection_id", collection.collection_id.to_string()))
     }
 
-    pub fn update_admin(
+    // Lists a cw721 contract under the next collection id, each contract is listed once
+    pub fn register_collection(
         deps: DepsMut,
-        info: MessageInfo,
-        new_admin: Addr
-    ) -> Result<Response, ContractError> {
-        let mut config = CONFIG.load(deps.storage)?;
-
-        if config.admin != info.sender {
-            return Err(ContractError::Unauthorized);
+        collection: NFTCollectionMsg,
+    ) -> Result<NFTCollectionResp, ContractError> {
+        let contract = validate_nft_contract(deps.as_ref(), &collection.contract)?;
+        if COLLECTION_BY_CONTRACT.has(deps.storage, &contract) {
+            return Err(ContractError::CollectionAlreadyExists);
         }
-    
-        config.admin = new_admin;
-        CONFIG.save(deps.storage, &config)?;
-        Ok(Response::new()
-                .add_attribute("action", "update_admin"))
+
+        let collection_id = LAST_COLLECTION_ID.load(deps.storage)? + 1;
+        let collection = NFTCollectionResp {
+            collection_id,
+            collection: collection.collection,
+            floor_price: collection.floor_price,
+            contract,
+            apy: collection.apy,
+            max_time: collection.max_time,
+            risk_tier: collection.risk_tier,
+            disabled: false,
+        };
+
+        NFT_COLLECTIONS.save(deps.storage, collection_id, &collection)?;
+        COLLECTION_BY_CONTRACT.save(deps.storage, &collection.contract, &collection_id)?;
+        LAST_COLLECTION_ID.save(deps.storage, &collection_id)?;
+        Ok(collection)
     }
 
-    pub fn update_interest(
+    // Only real NFT contracts answer the cw721 `ContractInfo` query
+    fn validate_nft_contract(deps: Deps, contract: &Addr) -> Result<Addr, ContractError> {
+        let contract = deps.api.addr_validate(contract.as_str())?;
+        query_nft_contract_info(deps, &contract).map_err(|_err| ContractError::InvalidNftContract)?;
+        Ok(contract)
+    }
+
+    // Updates the given fields only, active loans keep the terms they were taken under
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_collection(
         deps: DepsMut,
         info: MessageInfo,
-        interest: u128
+        collection_id: u16,
+        name: Option<String>,
+        contract: Option<Addr>,
+        apy: Option<u16>,
+        max_time: Option<u64>,
+        risk_tier: Option<u8>,
+        disabled: Option<bool>
     ) -> Result<Response, ContractError> {
-        let mut config = CONFIG.load(deps.storage)?;
+        assert_role(deps.as_ref(), &info.sender, Role::CollectionManager)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[collection_id])?;
 
-        if config.admin != info.sender {
-            return Err(ContractError::Unauthorized);
+        let mut collection = match NFT_COLLECTIONS.may_load(deps.storage, collection_id)? {
+            Some(collection) => collection,
+            None => return Err(ContractError::CollectionNotFound),
+        };
+
+        if let Some(contract) = contract {
+            let contract = validate_nft_contract(deps.as_ref(), &contract)?;
+            if contract != collection.contract {
+                // Escrowed NFTs are returned through the collection contract
+                if has_active_loans(deps.as_ref(), collection_id)? {
+                    return Err(ContractError::CollectionInUse);
+                }
+                if COLLECTION_BY_CONTRACT.has(deps.storage, &contract) {
+                    return Err(ContractError::CollectionAlreadyExists);
+                }
+                COLLECTION_BY_CONTRACT.remove(deps.storage, &collection.contract);
+                COLLECTION_BY_CONTRACT.save(deps.storage, &contract, &collection_id)?;
+                collection.contract = contract;
+            }
         }
-    
-        config.interest = interest;
-        CONFIG.save(deps.storage, &config)?;
+        if let Some(name) = name {
+            collection.collection = name;
+        }
+        if let Some(apy) = apy {
+            collection.apy = apy;
+        }
+        if let Some(max_time) = max_time {
+            collection.max_time = max_time;
+        }
+        if let Some(risk_tier) = risk_tier {
+            collection.risk_tier = risk_tier;
+        }
+        if let Some(disabled) = disabled {
+            collection.disabled = disabled;
+        }
+
+        NFT_COLLECTIONS.save(deps.storage, collection_id, &collection)?;
+
         Ok(Response::new()
-            .add_attribute("action", "update_interest"))
+            .add_attribute("action", "update_collection")
+            .add_attribute("collection_id", collection_id.to_string()))
     }
- 
-    pub fn repay(
+
+    // Delists a collection without active loans, its open offers are refunded to their lenders and expire
+    pub fn remove_collection(
         deps: DepsMut,
         info: MessageInfo,
         env: Env,
-        owner: Addr,
-        offer_id: u16,
-    ) -> Result<Response, ContractError>  {
-        // Load the denom
-        let denom = LEND_DENOM.load(deps.storage)?;
-        // Load the config
-        let config = CONFIG.load(deps.storage)?;
+        collection_id: u16
+    ) -> Result<Response, ContractError> {
+        assert_role(deps.as_ref(), &info.sender, Role::CollectionManager)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[collection_id])?;
 
-        // Load the offer from storage
-        let Some(offer) = offer_resps().may_load(deps.storage, (&owner, offer_id))? else {
-            return Err(ContractError::OfferNotFound); // Return error if offer does not exist
-        };
+        if !NFT_COLLECTIONS.has(deps.storage, collection_id) {
+            return Err(ContractError::CollectionNotFound);
+        }
+        if has_active_loans(deps.as_ref(), collection_id)? {
+            return Err(ContractError::CollectionInUse);
+        }
 
-        // Check if the sender is the owner of the offer
-        if offer.borrower != info.sender {
-            return Err(ContractError::InvalidBorrow);
+        let denom = LEND_DENOM.load(deps.storage)?;
+        let mut messages: Vec<CosmosMsg> = vec![];
+        let open_offers = offer_resps()
+            .idx
+            .collection
+            .prefix(collection_id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .filter(|entry| entry.as_ref().map_or(true, |offer| offer.status == OfferStatus::Open))
+            .collect::<StdResult<Vec<_>>>()?;
+        for offer in open_offers {
+            let expired = transition(&offer, OfferStatus::Expired)?;
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: offer.owner.to_string(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount: offer.amount.into(),
+                }],
+            }));
+            archive_offer(deps.storage, &env, expired, 0, 0)?;
+            withdraw_offer_stats(deps.storage, &offer)?;
         }
 
-        // Check if the offer was accepted
-        if !offer.accepted {
-            return Err(ContractError::OfferNotAccepted);
+        // Drop everything keyed by the collection id
+        let token_ids = TOKEN_APPRAISALS
+            .prefix(collection_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for token_id in token_ids {
+            TOKEN_APPRAISALS.remove(deps.storage, (collection_id, &token_id));
+        }
+        let traits = TRAIT_APPRAISALS
+            .sub_prefix(collection_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (trait_type, value) in traits {
+            TRAIT_APPRAISALS.remove(deps.storage, (collection_id, &trait_type, &value));
         }
+        for operation in Operation::ALL {
+            PAUSED_COLLECTION_OPERATIONS.remove(deps.storage, (collection_id, operation.key()));
+        }
+        let collection = NFT_COLLECTIONS.load(deps.storage, collection_id)?;
+        COLLECTION_BY_CONTRACT.remove(deps.storage, &collection.contract);
+        COLLECTION_STATS.remove(deps.storage, collection_id);
+        COLLECTION_LOANS.remove(deps.storage, collection_id);
+        NFT_COLLECTIONS.remove(deps.storage, collection_id);
 
-        // Get the collection associated with the offer
-        let collection = match NFT_COLLECTIONS.may_load(deps.storage, offer.collection_id)? {
-            Some(collection) => collection,
-            None => return Err(ContractError::CollectionNotFound),
-        };
+        Ok(Response::new()
+            .add_messages(messages)
+            .add_attribute("action", "remove_collection")
+            .add_attribute("collection_id", collection_id.to_string()))
+    }
 
-        let current_time = env.block.time.seconds();
-        // this is the case when the borrow couldn't repay fund in time
-        if offer.start_time + collection.max_time < current_time {
-            //  Send the NFT to the contract address
-            let msg = Cw721ExecuteMsg::TransferNft {
-                recipient: offer.owner.to_string(),
-                token_id: offer.token_id.to_string(),
-            };
+    fn has_active_loans(deps: Deps, collection_id: u16) -> StdResult<bool> {
+        Ok(COLLECTION_LOANS.may_load(deps.storage, collection_id)?.unwrap_or_default() > 0)
+    }
 
-            let execute_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: collection.contract.to_string(),
-                msg: to_binary(&msg)?,
-                funds: vec![],
-            });
-            
-            let messages: Vec<CosmosMsg> = vec![execute_msg];
-            // Offer remove
-            offer_resps().remove(deps.storage, (&offer.owner, offer_id));
+    // Counts a loan once against each collection it was made on or holds NFTs of
+    fn count_collection_loans(storage: &mut dyn Storage, loan: &OfferResp, opened: bool) -> StdResult<()> {
+        let mut collection_ids = loan_collection_ids(loan);
+        collection_ids.sort_unstable();
+        collection_ids.dedup();
+        for collection_id in collection_ids {
+            let loans = COLLECTION_LOANS.may_load(storage, collection_id)?.unwrap_or_default();
+            let loans = if opened { loans + 1 } else { loans.saturating_sub(1) };
+            COLLECTION_LOANS.save(storage, collection_id, &loans)?;
+        }
+        Ok(())
+    }
+
+    // The new admin only takes over once it accepts, a new proposal replaces the pending one
+    pub fn propose_admin(
+        deps: DepsMut,
+        info: MessageInfo,
+        new_admin: Addr
+    ) -> Result<Response, ContractError> {
+        assert_owner(deps.as_ref(), &info.sender)?;
+
+        let new_admin = deps.api.addr_validate(new_admin.as_str())?;
+        PENDING_ADMIN.save(deps.storage, &new_admin)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "propose_admin")
+            .add_attribute("pending_admin", new_admin))
+    }
+
+    pub fn accept_admin(
+        deps: DepsMut,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let Some(pending_admin) = PENDING_ADMIN.may_load(deps.storage)? else {
+            return Err(ContractError::NoPendingAdmin);
+        };
+
+        if pending_admin != info.sender {
+            return Err(ContractError::Unauthorized);
+        }
+
+        let mut config = CONFIG.load(deps.storage)?;
+        config.admin = pending_admin;
+        CONFIG.save(deps.storage, &config)?;
+        PENDING_ADMIN.remove(deps.storage);
+
+        Ok(Response::new()
+            .add_attribute("action", "accept_admin")
+            .add_attribute("admin", config.admin))
+    }
+
+    pub fn grant_role(
+        deps: DepsMut,
+        info: MessageInfo,
+        role: Role,
+        address: Addr
+    ) -> Result<Response, ContractError> {
+        assert_owner(deps.as_ref(), &info.sender)?;
+
+        let address = deps.api.addr_validate(address.as_str())?;
+        ROLES.save(deps.storage, (role.key(), &address), &true)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "grant_role")
+            .add_attribute("role", role.key())
+            .add_attribute("address", address))
+    }
+
+    pub fn revoke_role(
+        deps: DepsMut,
+        info: MessageInfo,
+        role: Role,
+        address: Addr
+    ) -> Result<Response, ContractError> {
+        assert_owner(deps.as_ref(), &info.sender)?;
+
+        ROLES.remove(deps.storage, (role.key(), &address));
+
+        Ok(Response::new()
+            .add_attribute("action", "revoke_role")
+            .add_attribute("role", role.key())
+            .add_attribute("address", address))
+    }
+
+    // Pays out accrued protocol fees, everything by default and to the sender unless told otherwise
+    pub fn withdraw_fees(
+        deps: DepsMut,
+        info: MessageInfo,
+        amount: Option<u128>,
+        recipient: Option<Addr>
+    ) -> Result<Response, ContractError> {
+        assert_role(deps.as_ref(), &info.sender, Role::FeeCollector)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[])?;
+
+        let denom = LEND_DENOM.load(deps.storage)?;
+        let accrued_fees = ACCRUED_FEES.may_load(deps.storage)?.unwrap_or_default();
+        let amount = amount.unwrap_or(accrued_fees);
+        if amount == 0 || amount > accrued_fees {
+            return Err(ContractError::InvalidFeeAmount);
+        }
+
+        let recipient = match recipient {
+            Some(recipient) => deps.api.addr_validate(recipient.as_str())?,
+            None => info.sender,
+        };
+
+        ACCRUED_FEES.save(deps.storage, &(accrued_fees - amount))?;
+
+        let message = BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom,
+                amount: amount.into(),
+            }],
+        };
+
+        Ok(Response::new()
+            .add_message(message)
+            .add_attribute("action", "withdraw_fees")
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("recipient", recipient))
+    }
+
+    // Pauses or resumes operations for every collection, or only for `collection_id`
+    pub fn set_paused(
+        deps: DepsMut,
+        info: MessageInfo,
+        operations: Vec<Operation>,
+        collection_id: Option<u16>,
+        paused: bool
+    ) -> Result<Response, ContractError> {
+        assert_role(deps.as_ref(), &info.sender, Role::Pauser)?;
+
+        if let Some(collection_id) = collection_id {
+            if !NFT_COLLECTIONS.has(deps.storage, collection_id) {
+                return Err(ContractError::CollectionNotFound);
+            }
+        }
+
+        // a pause the operation ignores would only show up in the pause state
+        if paused {
+            let settings = PAUSE_SETTINGS.may_load(deps.storage)?.unwrap_or_default();
+            if let Some(operation) = operations.iter().find(|operation| settings.stays_open(**operation)) {
+                return Err(ContractError::StaysOpenDuringPause { operation: operation.key().to_string() });
+            }
+        }
+
+        for operation in &operations {
+            match (collection_id, paused) {
+                (Some(collection_id), true) => PAUSED_COLLECTION_OPERATIONS.save(deps.storage, (collection_id, operation.key()), &true)?,
+                (Some(collection_id), false) => PAUSED_COLLECTION_OPERATIONS.remove(deps.storage, (collection_id, operation.key())),
+                (None, true) => PAUSED_OPERATIONS.save(deps.storage, operation.key(), &true)?,
+                (None, false) => PAUSED_OPERATIONS.remove(deps.storage, operation.key()),
+            }
+        }
+
+        let keys: Vec<&str> = operations.iter().map(|operation| operation.key()).collect();
+        Ok(Response::new()
+            .add_attribute("action", if paused { "pause" } else { "unpause" })
+            .add_attribute("operations", keys.join(","))
+            .add_attribute("collection_id", collection_id.map_or("all".to_string(), |id| id.to_string())))
+    }
+
+    pub fn update_pause_settings(
+        deps: DepsMut,
+        info: MessageInfo,
+        repay_open_during_pause: bool,
+        cancel_open_during_pause: bool
+    ) -> Result<Response, ContractError> {
+        assert_owner(deps.as_ref(), &info.sender)?;
+
+        // exits can only reopen once they are unpaused everywhere
+        let settings = PauseSettings { repay_open_during_pause, cancel_open_during_pause };
+        for operation in [Operation::Repay, Operation::Cancel] {
+            if settings.stays_open(operation) && is_paused_anywhere(deps.as_ref(), operation)? {
+                return Err(ContractError::Paused { operation: operation.key().to_string() });
+            }
+        }
+        PAUSE_SETTINGS.save(deps.storage, &settings)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "update_pause_settings")
+            .add_attribute("repay_open_during_pause", repay_open_during_pause.to_string())
+            .add_attribute("cancel_open_during_pause", cancel_open_during_pause.to_string()))
+    }
+
+    pub fn update_interest(
+        deps: DepsMut,
+        info: MessageInfo,
+        interest: u128
+    ) -> Result<Response, ContractError> {
+        assert_owner(deps.as_ref(), &info.sender)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[])?;
+        if interest > MAX_INTEREST {
+            return Err(ContractError::InvalidInterest);
+        }
+        let mut config = CONFIG.load(deps.storage)?;
+    
+        config.interest = interest;
+        CONFIG.save(deps.storage, &config)?;
+        Ok(Response::new()
+            .add_attribute("action", "update_interest"))
+    }
+ 
+    pub fn repay(
+        deps: DepsMut,
+        info: MessageInfo,
+        env: Env,
+        offer_id: u64,
+        max_payment: Option<u128>,
+    ) -> Result<Response, ContractError>  {
+        // Load the offer from storage
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
+            return Err(ContractError::OfferNotFound); // Return error if offer does not exist
+        };
+
+        assert_active(&offer)?;
+
+        // Whoever holds the borrower receipt may repay and gets the collateral back
+        let borrower = receipt_holder(deps.as_ref(), &offer)?;
+        if borrower != info.sender {
+            return Err(ContractError::InvalidBorrow);
+        }
+
+        let terms = loan_terms(deps.as_ref(), &offer)?;
+
+        let current_time = env.block.time.seconds();
+        // this is the case when the borrow couldn't repay fund in time
+        if offer.start_time + terms.max_time < current_time {
+            assert_not_paused(deps.as_ref(), Operation::Liquidate, &loan_collection_ids(&offer))?;
+
+            // Whoever holds the promissory note is entitled to the collateral
+            let lender = note_holder(deps.as_ref(), &offer)?;
+
+            //  Send the collateral to the lender
+            let mut messages = collateral_transfer_msgs(deps.as_ref(), &offer.collateral, &lender)?;
+            // nothing is owed any more, a payment sent along goes back
+            let denom = LEND_DENOM.load(deps.storage)?;
+            let payment = cw_utils::may_pay(&info, &denom).map_err(|_err| ContractError::DepositFail)?;
+            if !payment.is_zero() {
+                messages.push(refund_msg(&info.sender, &denom, payment.u128()));
+            }
+            let defaulted = transition(&offer, OfferStatus::Defaulted)?;
+            archive_offer(deps.storage, &env, defaulted, 0, 0)?;
+            close_loan_stats(deps.storage, &offer, 0, 0, true)?;
+            count_collection_loans(deps.storage, &offer, false)?;
 
             Ok(Response::new().add_messages(messages)
-                .add_attribute("action","repay_fail"))
+                .add_attribute("action","repay_fail")
+                .add_attributes(closed_position_attributes(&offer)))
         } else {
-            // Calculate reward
-            let reward = calculate_reward(offer.start_time, collection.apy, current_time, offer.amount);
+            assert_not_paused(deps.as_ref(), Operation::Repay, &loan_collection_ids(&offer))?;
+            settle_repayment(deps, info, env, offer, borrower, max_payment)
+        }
+    }
 
-            match cw_utils::must_pay(&info, &denom) {
-                Ok(payment) => {
-                    if payment.u128() != reward + offer.amount {
-                        return Err(ContractError::NotExactAmount);
-                    }
-                },
-                Err(_err) => return Err(ContractError::DepositFail),
-            };
+    pub fn repay_for(
+        deps: DepsMut,
+        info: MessageInfo,
+        env: Env,
+        offer_id: u64,
+        recipient: Option<Addr>,
+        max_payment: Option<u128>,
+    ) -> Result<Response, ContractError>  {
+        // Load the offer from storage
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
+            return Err(ContractError::OfferNotFound); // Return error if offer does not exist
+        };
 
-            // Send the NFT to the borrower
-            let msg = Cw721ExecuteMsg::TransferNft {
-                recipient: offer.borrower.into(),
-                token_id: offer.token_id.into(),
-            };
-            let execute_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: collection.contract.clone().into(),
-                msg: to_binary(&msg)?,
-                funds: vec![Coin {
-                    denom: LEND_DENOM.load(deps.storage)?,
-                    amount: (offer.amount + reward).into(),
-                }],
-            });
-            
+        assert_active(&offer)?;
 
-            // Send the repayment amount (loan amount + reward) to the offer owner
-            let payment_amount = offer.amount + reward * config.interest / 100;
+        // The collateral goes to the borrower unless they authorized someone else
+        let borrower = receipt_holder(deps.as_ref(), &offer)?;
+        let recipient = match recipient {
+            None => borrower,
+            Some(recipient) if recipient == borrower => borrower,
+            Some(recipient) => match &offer.repay_recipient {
+                Some(authorization) if authorization.authorized_by == borrower && authorization.recipient == recipient => recipient,
+                _ => return Err(ContractError::UnauthorizedRecipient),
+            },
+        };
 
-            let payment_coin = Coin {
-                denom: LEND_DENOM.load(deps.storage)?,
-                amount: payment_amount.into(),
-            };
-            let payment_msg = BankMsg::Send {
-                to_address: offer.owner.clone().into(),
-                amount: vec![payment_coin],
-            };
+        let terms = loan_terms(deps.as_ref(), &offer)?;
 
-            // Send the repayment amount (loan amount + reward) to the admin
-            let payment_amount_owner = reward * (100 - config.interest) / 100;
+        // Only the borrower can settle a loan past its due date
+        if offer.start_time + terms.max_time < env.block.time.seconds() {
+            return Err(ContractError::LoanExpired);
+        }
 
-            let payment_coin = Coin {
-                denom: LEND_DENOM.load(deps.storage)?,
-                amount: payment_amount_owner.into(),
-            };
+        assert_not_paused(deps.as_ref(), Operation::Repay, &loan_collection_ids(&offer))?;
+
+        settle_repayment(deps, info, env, offer, recipient, max_payment)
+    }
+
+    // Past the due date the note holder may take the collateral without waiting for the borrower
+    pub fn liquidate(
+        deps: DepsMut,
+        info: MessageInfo,
+        env: Env,
+        offer_id: u64,
+    ) -> Result<Response, ContractError>  {
+        // Load the offer from storage
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
+            return Err(ContractError::OfferNotFound); // Return error if offer does not exist
+        };
+
+        let liquidated = transition(&offer, OfferStatus::Liquidated)?;
+
+        let lender = note_holder(deps.as_ref(), &offer)?;
+        if lender != info.sender {
+            return Err(ContractError::Unauthorized);
+        }
+
+        let terms = loan_terms(deps.as_ref(), &offer)?;
+        if offer.start_time + terms.max_time >= env.block.time.seconds() {
+            return Err(ContractError::LoanNotExpired);
+        }
+
+        assert_not_paused(deps.as_ref(), Operation::Liquidate, &loan_collection_ids(&offer))?;
+
+        let messages = collateral_transfer_msgs(deps.as_ref(), &offer.collateral, &lender)?;
+        archive_offer(deps.storage, &env, liquidated, 0, 0)?;
+        close_loan_stats(deps.storage, &offer, 0, 0, true)?;
+        count_collection_loans(deps.storage, &offer, false)?;
+
+        Ok(Response::new()
+            .add_messages(messages)
+            .add_attribute("action", "liquidate")
+            .add_attribute("offer_id", offer_id.to_string())
+            .add_attributes(closed_position_attributes(&offer)))
+    }
+
+    pub fn authorize_repay_recipient(
+        deps: DepsMut,
+        info: MessageInfo,
+        offer_id: u64,
+        recipient: Option<Addr>,
+    ) -> Result<Response, ContractError>  {
+        // Load the offer from storage
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
+            return Err(ContractError::OfferNotFound); // Return error if offer does not exist
+        };
+
+        assert_active(&offer)?;
+
+        let borrower = receipt_holder(deps.as_ref(), &offer)?;
+        if borrower != info.sender {
+            return Err(ContractError::InvalidBorrow);
+        }
+
+        // The authorization lapses once the receipt changes hands
+        let repay_recipient = recipient
+            .map(|recipient| -> StdResult<_> {
+                Ok(RepayRecipient {
+                    authorized_by: borrower,
+                    recipient: deps.api.addr_validate(recipient.as_str())?,
+                })
+            })
+            .transpose()?;
+
+        offer_resps().replace(
+            deps.storage,
+            offer.offer_id,
+            Some(&OfferResp {
+                repay_recipient,
+                ..offer.clone()
+            }),
+            Some(&offer)
+        )?;
+
+        Ok(Response::new()
+            .add_attribute("action", "authorize_repay_recipient")
+            .add_attribute("offer_id", offer_id.to_string()))
+    }
 
-            let payment_msg_owner = BankMsg::Send {
-                to_address: config.admin.into(),
-                amount: vec![payment_coin],
+    // What repaying a loan at `at_time` costs, `settle_repayment` keeps exactly `total_due`
+    pub fn repay_quote(deps: Deps, offer: &OfferResp, at_time: u64) -> Result<RepayQuoteResp, ContractError> {
+        assert_active(offer)?;
+        let terms = loan_terms(deps, offer)?;
+        let config = CONFIG.load(deps.storage)?;
+
+        let interest = calculate_reward(offer.start_time, terms.apy, at_time, offer.amount);
+        let due_date = offer.start_time + terms.max_time;
+
+        Ok(RepayQuoteResp {
+            loan_id: offer.offer_id,
+            principal: offer.amount,
+            interest,
+            protocol_fee: interest * (100 - config.interest) / 100,
+            late_fee: 0,
+            total_due: offer.amount + interest,
+            due_date,
+            overdue: at_time > due_date,
+        })
+    }
+
+    // Takes the amount due, pays out lender and fees, refunds the excess to the payer
+    // and releases the collateral to `recipient`
+    fn settle_repayment(
+        deps: DepsMut,
+        info: MessageInfo,
+        env: Env,
+        offer: OfferResp,
+        recipient: Addr,
+        max_payment: Option<u128>,
+    ) -> Result<Response, ContractError> {
+        // Load the denom
+        let denom = LEND_DENOM.load(deps.storage)?;
+
+        // Whoever holds the promissory note is entitled to the proceeds
+        let lender = note_holder(deps.as_ref(), &offer)?;
+
+        let quote = repay_quote(deps.as_ref(), &offer, env.block.time.seconds())?;
+
+        // Interest keeps growing until the tx lands, the borrower caps what they accept to pay
+        if let Some(max_payment) = max_payment {
+            if quote.total_due > max_payment {
+                return Err(ContractError::MaxPaymentExceeded { due: quote.total_due, max_payment });
+            }
+        }
+
+        let payment = match cw_utils::must_pay(&info, &denom) {
+            Ok(payment) => payment.u128(),
+            Err(_err) => return Err(ContractError::DepositFail),
+        };
+        if payment < quote.total_due {
+            return Err(ContractError::InsufficientPayment { due: quote.total_due });
+        }
+
+        // Send the collateral back to the borrower
+        let collateral_msgs = collateral_transfer_msgs(deps.as_ref(), &offer.collateral, &recipient)?;
+
+        // Send the loan amount and the interest left after protocol fees to the lender,
+        // so the rounding of the split never strands funds in the contract
+        let payment_amount = offer.amount + quote.interest - quote.protocol_fee;
+
+        let payment_coin = Coin {
+            denom: denom.clone(),
+            amount: payment_amount.into(),
+        };
+        let payment_msg = BankMsg::Send {
+            to_address: lender.into(),
+            amount: vec![payment_coin],
+        };
+
+        // Keep the rest of the reward as protocol fees for the fee collector
+        let protocol_fee = quote.protocol_fee;
+        let accrued_fees = ACCRUED_FEES.may_load(deps.storage)?.unwrap_or_default();
+        ACCRUED_FEES.save(deps.storage, &(accrued_fees + protocol_fee))?;
+
+        let repaid = transition(&offer, OfferStatus::Repaid)?;
+        archive_offer(deps.storage, &env, repaid, quote.total_due, protocol_fee)?;
+        close_loan_stats(deps.storage, &offer, quote.interest - protocol_fee, protocol_fee, false)?;
+        count_collection_loans(deps.storage, &offer, false)?;
+
+        let mut messages = collateral_msgs;
+        messages.push(CosmosMsg::Bank(payment_msg));
+        let refund = payment - quote.total_due;
+        if refund > 0 {
+            messages.push(refund_msg(&info.sender, &denom, refund));
+        }
+
+        // Construct anxs
+        Ok(Response::new()
+            .add_messages(messages)
+            .add_attribute("action", "repay success")
+            .add_attribute("refund", refund.to_string())
+            .add_attribute("payer", info.sender)
+            .add_attribute("recipient", recipient)
+            .add_attributes(closed_position_attributes(&offer))
+        )
+    }
+
+    fn refund_msg(payer: &Addr, denom: &str, amount: u128) -> CosmosMsg {
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: payer.to_string(),
+            amount: vec![Coin { denom: denom.to_string(), amount: amount.into() }],
+        })
+    }
+
+    // The only place offer statuses change: open offers are taken, cancelled or expire,
+    // loans are repaid, defaulted or liquidated, closed offers are archived and never move again
+    fn transition(offer: &OfferResp, to: OfferStatus) -> Result<OfferResp, ContractError> {
+        let allowed = match offer.status {
+            OfferStatus::Open => matches!(to, OfferStatus::Active | OfferStatus::Cancelled | OfferStatus::Expired),
+            OfferStatus::Active => matches!(to, OfferStatus::Repaid | OfferStatus::Defaulted | OfferStatus::Liquidated),
+            _ => false,
+        };
+        if !allowed {
+            return Err(status_error(offer.status));
+        }
+        Ok(OfferResp { status: to, ..offer.clone() })
+    }
+
+    // Notes and receipts of a closed loan stay with their holders, indexers learn they are void from this
+    fn closed_position_attributes(loan: &OfferResp) -> Vec<(&'static str, String)> {
+        let mut attributes = vec![];
+        if loan.note_contract.is_some() {
+            attributes.push(("closed_note", loan.offer_id.to_string()));
+        }
+        if loan.receipt_contract.is_some() {
+            attributes.push(("closed_receipt", loan.offer_id.to_string()));
+        }
+        attributes
+    }
+
+    // Moves an offer that reached its final status out of the live offers into the archive
+    fn archive_offer(storage: &mut dyn Storage, env: &Env, closed: OfferResp, amount_paid: u128, fees: u128) -> StdResult<()> {
+        offer_resps().remove(storage, closed.offer_id)?;
+        let archived = ArchivedOfferResp {
+            outcome: closed.status,
+            closed_at: env.block.time.seconds(),
+            amount_paid,
+            fees,
+            offer: closed,
+        };
+        archived_offers().save(storage, archived.offer.offer_id, &archived)
+    }
+
+    // Handlers acting on a running loan without closing it
+    fn assert_active(offer: &OfferResp) -> Result<(), ContractError> {
+        if offer.status != OfferStatus::Active {
+            return Err(status_error(offer.status));
+        }
+        Ok(())
+    }
+
+    fn status_error(status: OfferStatus) -> ContractError {
+        match status {
+            OfferStatus::Open => ContractError::OfferNotAccepted,
+            OfferStatus::Active => ContractError::OfferAlreadyAccepted,
+            _ => ContractError::OfferClosed,
+        }
+    }
+
+    // Applies `update` to the protocol, collection and lender totals of an offer
+    fn update_stats(storage: &mut dyn Storage, offer: &OfferResp, update: impl Fn(&mut LoanStats)) -> StdResult<()> {
+        let mut stats = STATS.may_load(storage)?.unwrap_or_default();
+        update(&mut stats);
+        STATS.save(storage, &stats)?;
+
+        let mut stats = COLLECTION_STATS.may_load(storage, offer.collection_id)?.unwrap_or_default();
+        update(&mut stats);
+        COLLECTION_STATS.save(storage, offer.collection_id, &stats)?;
+
+        let mut stats = LENDER_STATS.may_load(storage, &offer.owner)?.unwrap_or_default();
+        update(&mut stats);
+        LENDER_STATS.save(storage, &offer.owner, &stats)
+    }
+
+    fn update_borrower_stats(storage: &mut dyn Storage, borrower: &Addr, update: impl Fn(&mut LoanStats)) -> StdResult<()> {
+        let mut stats = BORROWER_STATS.may_load(storage, borrower)?.unwrap_or_default();
+        update(&mut stats);
+        BORROWER_STATS.save(storage, borrower, &stats)
+    }
+
+    // An open offer was cancelled or expired, its funds went back to the lender
+    fn withdraw_offer_stats(storage: &mut dyn Storage, offer: &OfferResp) -> StdResult<()> {
+        update_stats(storage, offer, |stats| {
+            stats.open_offers = stats.open_offers.saturating_sub(1);
+            stats.tvl = stats.tvl.saturating_sub(offer.amount);
+        })
+    }
+
+    // A loan was repaid with `interest` going to the lender and `fees` to the protocol, or defaulted on
+    fn close_loan_stats(storage: &mut dyn Storage, offer: &OfferResp, interest: u128, fees: u128, defaulted: bool) -> StdResult<()> {
+        let close_loan = |stats: &mut LoanStats| {
+            stats.active_loans = stats.active_loans.saturating_sub(1);
+            stats.outstanding_principal = stats.outstanding_principal.saturating_sub(offer.amount);
+            stats.interest_earned += interest;
+            stats.fees_collected += fees;
+            stats.defaults += u64::from(defaulted);
+        };
+        update_stats(storage, offer, close_loan)?;
+        match &offer.borrower {
+            Some(borrower) => update_borrower_stats(storage, borrower, close_loan),
+            None => Ok(()),
+        }
+    }
+
+    // Terms of an active loan, loans from before terms were recorded follow their collection
+    fn loan_terms(deps: Deps, offer: &OfferResp) -> Result<LoanTerms, ContractError> {
+        if let Some(terms) = &offer.loan_terms {
+            return Ok(terms.clone());
+        }
+        match NFT_COLLECTIONS.may_load(deps.storage, offer.collection_id)? {
+            Some(collection) => Ok(LoanTerms { apy: collection.apy, max_time: collection.max_time }),
+            None => Err(ContractError::CollectionNotFound),
+        }
+    }
+
+    // Current lender of a loan: the note holder if a note was minted, the offer owner otherwise
+    fn note_holder(deps: Deps, offer: &OfferResp) -> StdResult<Addr> {
+        match &offer.note_contract {
+            Some(note_contract) => position_holder(deps, note_contract, offer.offer_id),
+            None => Ok(offer.owner.clone()),
+        }
+    }
+
+    // Current borrower of a loan: the receipt holder if a receipt was minted, the borrower otherwise
+    fn receipt_holder(deps: Deps, offer: &OfferResp) -> StdResult<Addr> {
+        match &offer.receipt_contract {
+            Some(receipt_contract) => position_holder(deps, receipt_contract, offer.offer_id),
+            None => offer.borrower.clone().ok_or_else(|| StdError::not_found("borrower")),
+        }
+    }
+
+    fn position_holder(deps: Deps, position_contract: &Addr, offer_id: u64) -> StdResult<Addr> {
+        let holder = query_nft_owner(deps, position_contract, &offer_id.to_string())?;
+        deps.api.addr_validate(&holder)
+    }
+
+    // Notes and receipts share the offer id as token id
+    fn mint_position_msg(position_contract: &Addr, offer_id: u64, owner: &Addr) -> StdResult<CosmosMsg> {
+        let msg = cw721_base::ExecuteMsg::<cw721_base::Extension, Empty>::Mint {
+            token_id: offer_id.to_string(),
+            owner: owner.to_string(),
+            token_uri: None,
+            extension: None,
+        };
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: position_contract.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        }))
+    }
+
+    // Transfers every escrowed NFT of a loan to `recipient`
+    fn collateral_transfer_msgs(deps: Deps, collateral: &[Collateral], recipient: &Addr) -> Result<Vec<CosmosMsg>, ContractError> {
+        let mut messages: Vec<CosmosMsg> = vec![];
+        for item in collateral {
+            let collection = match NFT_COLLECTIONS.may_load(deps.storage, item.collection_id)? {
+                Some(collection) => collection,
+                None => return Err(ContractError::CollectionNotFound),
             };
 
-            // Offer remove
-            offer_resps().remove(deps.storage, (&offer.owner.clone(), offer_id.clone()));
-    
-            // Construct anxs
-            Ok(Response::new()
-                .add_message(execute_msg)
-                .add_message(payment_msg)
-                .add_message(payment_msg_owner)
-                .add_attribute("action", "repay success")
-            )
+            let msg = Cw721ExecuteMsg::TransferNft {
+                recipient: recipient.to_string(),
+                token_id: item.token_id.to_string(),
+            };
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: collection.contract.to_string(),
+                msg: to_json_binary(&msg)?,
+                funds: vec![],
+            }));
         }
+        Ok(messages)
     }
 
     // Function to calculate reward
     pub fn calculate_reward(start_time: u64, apy: u16, current_time: u64, amount: u128) -> u128 {
-        // Calculate elapsed time in seconds
-        let elapsed_time_seconds = current_time - start_time;
+        // Calculate elapsed time in seconds, none before the loan started
+        let elapsed_time_seconds = current_time.saturating_sub(start_time);
+
+        amount * elapsed_time_seconds as u128 * apy as u128 / (365 * 24 * 60 * 60) as u128
+    }
+
+    pub fn set_token_appraisal(
+        deps: DepsMut,
+        info: MessageInfo,
+        collection_id: u16,
+        token_id: String,
+        multiplier: Option<u16>
+    ) -> Result<Response, ContractError> {
+        assert_role(deps.as_ref(), &info.sender, Role::Appraiser)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[collection_id])?;
+
+        if !NFT_COLLECTIONS.has(deps.storage, collection_id) {
+            return Err(ContractError::CollectionNotFound);
+        }
+
+        // A missing multiplier clears the override and falls back to traits/floor
+        match multiplier {
+            Some(0) => return Err(ContractError::InvalidMultiplier),
+            Some(multiplier) => TOKEN_APPRAISALS.save(deps.storage, (collection_id, &token_id), &multiplier)?,
+            None => TOKEN_APPRAISALS.remove(deps.storage, (collection_id, &token_id)),
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_token_appraisal")
+            .add_attribute("collection_id", collection_id.to_string())
+            .add_attribute("token_id", token_id))
+    }
+
+    pub fn set_trait_appraisal(
+        deps: DepsMut,
+        info: MessageInfo,
+        collection_id: u16,
+        trait_type: String,
+        value: String,
+        multiplier: Option<u16>
+    ) -> Result<Response, ContractError> {
+        assert_role(deps.as_ref(), &info.sender, Role::Appraiser)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[collection_id])?;
+
+        if !NFT_COLLECTIONS.has(deps.storage, collection_id) {
+            return Err(ContractError::CollectionNotFound);
+        }
+
+        match multiplier {
+            Some(0) => return Err(ContractError::InvalidMultiplier),
+            Some(multiplier) => TRAIT_APPRAISALS.save(deps.storage, (collection_id, &trait_type, &value), &multiplier)?,
+            None => TRAIT_APPRAISALS.remove(deps.storage, (collection_id, &trait_type, &value)),
+        }
+
+        Ok(Response::new()
+            .add_attribute("action", "set_trait_appraisal")
+            .add_attribute("collection_id", collection_id.to_string())
+            .add_attribute("trait_type", trait_type)
+            .add_attribute("value", value))
+    }
+
+    pub fn set_note_contract(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        note_contract: Addr
+    ) -> Result<Response, ContractError> {
+        assert_owner(deps.as_ref(), &info.sender)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[])?;
+
+        let note_contract = assert_minted_by_self(deps.as_ref(), &env, &note_contract)?;
+        NOTE_CONTRACT.save(deps.storage, &note_contract)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_note_contract")
+            .add_attribute("note_contract", note_contract))
+    }
+
+    pub fn set_receipt_contract(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        receipt_contract: Addr
+    ) -> Result<Response, ContractError> {
+        assert_owner(deps.as_ref(), &info.sender)?;
+        assert_not_paused(deps.as_ref(), Operation::Admin, &[])?;
+
+        let receipt_contract = assert_minted_by_self(deps.as_ref(), &env, &receipt_contract)?;
+        RECEIPT_CONTRACT.save(deps.storage, &receipt_contract)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "set_receipt_contract")
+            .add_attribute("receipt_contract", receipt_contract))
+    }
+
+    // Notes and receipts are minted on borrow, so this contract has to be the minter
+    fn assert_minted_by_self(deps: Deps, env: &Env, contract: &Addr) -> Result<Addr, ContractError> {
+        let contract = deps.api.addr_validate(contract.as_str())?;
+        let minter: cw721_base::MinterResponse = deps
+            .querier
+            .query_wasm_smart(&contract, &cw721_base::QueryMsg::<Empty>::Minter {})
+            .map_err(|_err| ContractError::InvalidMinterContract)?;
+        if minter.minter != Some(env.contract.address.to_string()) {
+            return Err(ContractError::InvalidMinterContract);
+        }
+        Ok(contract)
+    }
 
-        let reward = amount * elapsed_time_seconds as u128 * apy as u128 / (365 * 24 * 60 * 60) as u128;
+    // Fails if `operation` is paused globally or for any of `collection_ids`
+    fn assert_not_paused(deps: Deps, operation: Operation, collection_ids: &[u16]) -> Result<(), ContractError> {
+        let settings = PAUSE_SETTINGS.may_load(deps.storage)?.unwrap_or_default();
+        if settings.stays_open(operation) {
+            return Ok(());
+        }
+
+        let paused = PAUSED_OPERATIONS.has(deps.storage, operation.key())
+            || collection_ids
+                .iter()
+                .any(|collection_id| PAUSED_COLLECTION_OPERATIONS.has(deps.storage, (*collection_id, operation.key())));
+        if paused {
+            return Err(ContractError::Paused { operation: operation.key().to_string() });
+        }
+        Ok(())
+    }
 
-        reward
+    // Paused globally or for any collection
+    fn is_paused_anywhere(deps: Deps, operation: Operation) -> StdResult<bool> {
+        if PAUSED_OPERATIONS.has(deps.storage, operation.key()) {
+            return Ok(true);
+        }
+        for entry in PAUSED_COLLECTION_OPERATIONS.keys(deps.storage, None, None, Order::Ascending) {
+            let (_collection_id, key) = entry?;
+            if key == operation.key() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // Every collection a loan touches: its offer collection and those of its collateral
+    fn loan_collection_ids(offer: &OfferResp) -> Vec<u16> {
+        let mut collection_ids: Vec<u16> = offer.collateral.iter().map(|item| item.collection_id).collect();
+        collection_ids.push(offer.collection_id);
+        collection_ids
+    }
+
+    fn assert_owner(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+        let config = CONFIG.load(deps.storage)?;
+
+        if config.admin != sender {
+            return Err(ContractError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    // The owner implicitly holds every role
+    pub fn has_role(deps: Deps, sender: &Addr, role: Role) -> StdResult<bool> {
+        let config = CONFIG.load(deps.storage)?;
+        Ok(config.admin == sender || ROLES.has(deps.storage, (role.key(), sender)))
+    }
+
+    fn assert_role(deps: Deps, sender: &Addr, role: Role) -> Result<(), ContractError> {
+        if !has_role(deps, sender, role)? {
+            return Err(ContractError::Unauthorized);
+        }
+        Ok(())
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     use QueryMsg::*;
 
     match msg {
-        OfferList {page_size, page_num} => query::offer_list(deps,page_size, page_num), // indexing
+        OfferList { start_after, limit, viewer } => query::offer_list(deps, start_after, limit, viewer),
         OfferByID { offer_id } => query::offer_by_id(deps, offer_id),
-        OffersByOwner {owner, page_size, page_num} => query::get_offers_by_owner(deps, owner.as_str(), page_size, page_num), 
-        OffersAcceptByBorrow {borrower, page_size, page_num} => query::get_offers_accept_by_borrower(deps, borrower, page_size, page_num), 
-
-        OffersByPrice {page, page_size, limit, sort} => query::get_offers_by_price(deps,page, page_size, limit, sort),
+        OffersByOwner { owner, start_after, limit } => query::get_offers_by_owner(deps, owner, start_after, limit),
+        OffersAcceptByBorrow { borrower, start_after, limit } => query::get_offers_accept_by_borrower(deps, borrower, start_after, limit),
+        OffersByCollection { collection_id, start_after, limit, viewer } => query::offers_by_collection(deps, collection_id, start_after, limit, viewer),
+        OffersByStatus { status, start_after, limit, viewer } => query::offers_by_status(deps, status, start_after, limit, viewer),
+        RepayQuote { loan_id, at_time } => query::repay_quote(deps, env, loan_id, at_time),
+        SimulateBorrow { offer_id, borrower, collateral } => query::simulate_borrow(deps, offer_id, borrower, collateral),
+        SimulateLend { amount, collection_id, token_id, bundle } => query::simulate_lend(deps, amount, collection_id, token_id, bundle),
+        ArchivedOffer { offer_id } => query::archived_offer(deps, offer_id),
+        HistoryByLender { lender, start_after, limit } => query::history_by_lender(deps, lender, start_after, limit),
+        HistoryByBorrower { borrower, start_after, limit } => query::history_by_borrower(deps, borrower, start_after, limit),
+        HistoryByCollection { collection_id, start_after, limit } => query::history_by_collection(deps, collection_id, start_after, limit),
+
+        LoansDueBetween { from, to, start_after, limit } => query::loans_due_between(deps, from, to, start_after, limit),
+        OverdueLoans { start_after, limit } => query::overdue_loans(deps, env, start_after, limit),
+        OffersByPrice { min_amount, sort, start_after, limit, viewer } => query::get_offers_by_price(deps, min_amount, sort, start_after, limit, viewer),
+        OrderBook { collection_id, sort, start_after, limit, viewer } => query::order_book(deps, collection_id, sort, start_after, limit, viewer),
+        OrderBookDepth { collection_id, levels } => query::order_book_depth(deps, collection_id, levels),
+        Stats {} => query::stats(deps),
+        CollectionStats { collection_id } => query::collection_stats(deps, collection_id),
+        UserStats { address } => query::user_stats(deps, address),
         CollectionByID {collection_id} => query::collection_by_id(deps, collection_id),
+        CollectionByContract { contract } => query::collection_by_contract(deps, contract),
+        Collections { start_after, limit, active_only, risk_tier, contract } => query::collections(deps, start_after, limit, active_only, risk_tier, contract),
         QueryAdmin {} => query::query_admin(deps),
+        Config {} => query::config(deps),
+        PendingAdmin {} => query::pending_admin(deps),
+        Roles { address } => query::roles(deps, address),
+        AccruedFees {} => query::accrued_fees(deps),
+        PauseState {} => query::pause_state(deps),
+        NoteContract {} => query::note_contract(deps),
+        PositionStatus { offer_id } => query::position_status(deps, offer_id),
+        ReceiptContract {} => query::receipt_contract(deps),
+        AppraisedValue { collection_id, token_id } => query::appraised_value(deps, collection_id, token_id),
+        OffersByToken { collection_id, token_id, start_after, limit, viewer } => query::get_offers_by_token(deps, collection_id, token_id, start_after, limit, viewer),
     }
 }
 
 mod query {
     use super::*;
 
-    //  query range offers using indexing
-    pub fn offer_list(deps: Deps, page_size: u16, page_num: u16) -> StdResult<Binary> {
-        let to_skip_usize = usize::from(page_num * page_size - page_size);
+    // Private offers are only listed for the borrowers they were made for
+    fn is_visible(offer: &OfferResp, viewer: Option<&Addr>) -> bool {
+        match &offer.allowed_borrowers {
+            Some(allowed_borrowers) => viewer.is_some_and(|viewer| allowed_borrowers.contains(viewer)),
+            None => true,
+        }
+    }
 
-        let offer_data: Vec<_> = offer_resps()
-            .range(deps.storage, None, None, Order::Ascending)
-            .collect::<StdResult<Vec<_>>>()?
-            .into_iter()
-            .skip(to_skip_usize)
-            .take(page_size.into())
-            .map(|entry| entry.1)
-            .collect();
-        
-        Ok(to_binary(&offer_data)?)
+    // Cursor of the next page, only when this page is full
+    fn next_start_after<T, C>(entries: &[T], limit: usize, cursor: impl Fn(&T) -> C) -> Option<C> {
+        if entries.len() < limit {
+            return None;
+        }
+        entries.last().map(cursor)
+    }
+
+    fn offer_list_resp(offers: Vec<OfferResp>, limit: usize) -> OfferListResp {
+        let next_start_after = next_start_after(&offers, limit, |offer| offer.offer_id);
+        OfferListResp { offers, next_start_after }
+    }
+
+    // offers ordered by id
+    pub fn offer_list(deps: Deps, start_after: Option<u64>, limit: Option<u32>, viewer: Option<Addr>) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let offers = offer_resps()
+            .range(deps.storage, start, None, Order::Ascending)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .filter(|entry| entry.as_ref().map_or(true, |offer| is_visible(offer, viewer.as_ref())))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        to_json_binary(&offer_list_resp(offers, limit))
     }
     
-    pub fn offer_by_id(deps: Deps, offer_id: u16) -> StdResult<Binary> {
-        let Some((_pk, offer)): Option<(_, OfferResp)> = offer_resps().idx.id.item(deps.storage, offer_id)? else {
+    pub fn offer_by_id(deps: Deps, offer_id: u64) -> StdResult<Binary> {
+        let Some(offer) = offer_resps().may_load(deps.storage, offer_id)? else {
             return Err(StdError::GenericErr { msg: "Invalid offer ID".to_string() });
         };
 
-        Ok(to_binary(&offer)?)
+        to_json_binary(&offer)
     }
     
-    pub fn get_offers_by_owner(deps: Deps, owner: &str, page_size: u16, page_num: u16) -> StdResult<Binary> {
-        let valid_owner = deps.api.addr_validate(owner)?;
-        let to_skip_usize = usize::from(page_num * page_size - page_size);
+    pub fn get_offers_by_owner(deps: Deps, owner: Addr, start_after: Option<u64>, limit: Option<u32>) -> StdResult<Binary> {
+        let valid_owner = deps.api.addr_validate(owner.as_str())?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let offers = offer_resps()
+            .idx
+            .owner
+            .prefix(valid_owner)
+            .range(deps.storage, start, None, Order::Ascending)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        to_json_binary(&offer_list_resp(offers, limit))
+    }
 
-        let offer_data: Vec<_> = offer_resps()
-            .prefix(&valid_owner)
-            .range(deps.storage, None, None, Order::Ascending)
-            .collect::<StdResult<Vec<_>>>()?
-            .into_iter()
-            .skip(to_skip_usize)
-            .take(page_size.into())
-            .map(|entry| entry.1)
-            .collect();
-        
-        Ok(to_binary(&offer_data)?)
+    // offers above `min_amount` walked through the price index, ties ordered by id
+    pub fn get_offers_by_price(
+        deps: Deps,
+        min_amount: u128,
+        sort: bool,
+        start_after: Option<PriceCursor>,
+        limit: Option<u32>,
+        viewer: Option<Addr>,
+    ) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        // every (min_amount, id) key sorts before the first amount above it
+        let floor = Some(Bound::exclusive((min_amount, u64::MAX)));
+        let cursor = start_after.map(|cursor| Bound::exclusive((cursor.amount, cursor.offer_id)));
+        let (min, max, order) = if sort {
+            (floor, cursor, Order::Descending)
+        } else {
+            (cursor.or(floor), None, Order::Ascending)
+        };
+
+        let offers = offer_resps()
+            .idx
+            .price
+            .range(deps.storage, min, max, order)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .filter(|entry| entry.as_ref().map_or(true, |offer| {
+                offer.amount > min_amount && !offer.status.is_closed() && is_visible(offer, viewer.as_ref())
+            }))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let next_start_after = next_start_after(&offers, limit, |offer| PriceCursor { amount: offer.amount, offer_id: offer.offer_id });
+        to_json_binary(&OffersByPriceResp { offers, next_start_after })
     }
+    
+    // open offers of a collection walked through its order book, ties ordered by id
+    pub fn order_book(
+        deps: Deps,
+        collection_id: u16,
+        sort: bool,
+        start_after: Option<PriceCursor>,
+        limit: Option<u32>,
+        viewer: Option<Addr>,
+    ) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let cursor = start_after.map(|cursor| Bound::exclusive((cursor.amount, cursor.offer_id)));
+        let (min, max, order) = if sort {
+            (None, cursor, Order::Descending)
+        } else {
+            (cursor, None, Order::Ascending)
+        };
 
-    pub fn get_offers_by_price(deps: Deps,page_size:u16, page_num: u16, limit: u128, sort: bool) -> StdResult<Binary> {
-        let to_skip_usize = usize::from(page_num * page_size - page_size);
-        
-        let  mut resp_offers: Vec<_> = offer_resps()
-            .range(deps.storage, None, None, Order::Ascending)
-            .collect::<StdResult<Vec<_>>>()?
-            .into_iter()
-            .filter_map(|entry| {
-                let x = entry.1.clone();
-                if x.amount > limit
-                {
-                   Some(x)
-                } else {
-                   None
-                }
-            })
-            .collect();
-        
-         // Sort offers based on price if `sort` flag is set to true
-        if sort {
-            resp_offers.sort_by(|a, b| {
-                // Compare offers based on their amount (price) in reverse order (high to low)
-                b.amount.cmp(&a.amount)
-            });
+        let offers = offer_resps()
+            .idx
+            .book
+            .sub_prefix((collection_id, OfferStatus::Open.key().to_string()))
+            .range(deps.storage, min, max, order)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .filter(|entry| entry.as_ref().map_or(true, |offer| is_visible(offer, viewer.as_ref())))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let next_start_after = next_start_after(&offers, limit, |offer| PriceCursor { amount: offer.amount, offer_id: offer.offer_id });
+        to_json_binary(&OffersByPriceResp { offers, next_start_after })
+    }
+
+    // open offers sit at the very end of the due index, `to` stops before them
+    pub fn loans_due_between(deps: Deps, from: u64, to: u64, start_after: Option<DueCursor>, limit: Option<u32>) -> StdResult<Binary> {
+        let min = match start_after {
+            Some(cursor) => Bound::exclusive((cursor.due_date, cursor.loan_id)),
+            None => Bound::inclusive((from, 0)),
+        };
+        let max = Bound::inclusive((to.min(u64::MAX - 1), u64::MAX));
+        loans_due(deps, min, max, limit)
+    }
+
+    // loans due before the current block can be liquidated
+    pub fn overdue_loans(deps: Deps, env: Env, start_after: Option<DueCursor>, limit: Option<u32>) -> StdResult<Binary> {
+        let min = start_after.map_or(Bound::inclusive((0, 0)), |cursor| Bound::exclusive((cursor.due_date, cursor.loan_id)));
+        let max = Bound::exclusive((env.block.time.seconds(), 0));
+        loans_due(deps, min, max, limit)
+    }
+
+    fn loans_due(deps: Deps, min: Bound<(u64, u64)>, max: Bound<(u64, u64)>, limit: Option<u32>) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        let loans = offer_resps()
+            .idx
+            .due
+            .range(deps.storage, Some(min), Some(max), Order::Ascending)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let next_start_after = next_start_after(&loans, limit, |loan| DueCursor {
+            due_date: loan.due_date().unwrap_or(u64::MAX),
+            loan_id: loan.offer_id,
+        });
+        to_json_binary(&LoansDueResp { loans, next_start_after })
+    }
+
+    // stops once `levels` distinct amounts are collected
+    pub fn order_book_depth(deps: Deps, collection_id: u16, levels: Option<u32>) -> StdResult<Binary> {
+        let max_levels = levels.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        let mut depth: Vec<DepthLevel> = vec![];
+        for entry in offer_resps()
+            .idx
+            .book
+            .sub_prefix((collection_id, OfferStatus::Open.key().to_string()))
+            .range(deps.storage, None, None, Order::Descending)
+        {
+            let (_id, offer) = entry?;
+            if !is_visible(&offer, None) {
+                continue;
+            }
+            match depth.last_mut() {
+                Some(level) if level.amount == offer.amount => {
+                    level.offers += 1;
+                    level.total += offer.amount;
+                },
+                _ => {
+                    if depth.len() == max_levels {
+                        break;
+                    }
+                    depth.push(DepthLevel { amount: offer.amount, offers: 1, total: offer.amount });
+                },
+            }
+        }
+
+        to_json_binary(&OrderBookDepthResp { collection_id, levels: depth })
+    }
+
+    // loans taken by a borrower, ordered by id
+    pub fn get_offers_accept_by_borrower(deps: Deps, borrower: Addr, start_after: Option<u64>, limit: Option<u32>) -> StdResult<Binary> {
+        let valid_borrower = deps.api.addr_validate(borrower.as_str())?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let offers = offer_resps()
+            .idx
+            .borrower
+            .prefix(valid_borrower)
+            .range(deps.storage, start, None, Order::Ascending)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .filter(|entry| entry.as_ref().map_or(true, |offer| offer.status == OfferStatus::Active))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        to_json_binary(&offer_list_resp(offers, limit))
+    }
+
+    // offers and loans of a collection, ordered by id
+    pub fn offers_by_collection(
+        deps: Deps,
+        collection_id: u16,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        viewer: Option<Addr>,
+    ) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let offers = offer_resps()
+            .idx
+            .collection
+            .prefix(collection_id)
+            .range(deps.storage, start, None, Order::Ascending)
+            .map(|entry| entry.map(|(_id, offer)| offer))
+            .filter(|entry| entry.as_ref().map_or(true, |offer| is_visible(offer, viewer.as_ref())))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        to_json_binary(&offer_list_resp(offers, limit))
+    }
+
+    pub fn offers_by_status(
+        deps: Deps,
+        status: OfferStatus,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        viewer: Option<Addr>,
+    ) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        // closed offers only live in the archive
+        let offers = if status.is_closed() {
+            archived_offers()
+                .idx
+                .outcome
+                .prefix(status.key().to_string())
+                .range(deps.storage, start, None, Order::Ascending)
+                .map(|entry| entry.map(|(_id, archived)| archived.offer))
+                .filter(|entry| entry.as_ref().map_or(true, |offer| is_visible(offer, viewer.as_ref())))
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?
         } else {
-            // Sort offers based on price in ascending order (low to high)
-            resp_offers.sort_by(|a, b| {
-                // Compare offers based on their amount (price)
-                a.amount.cmp(&b.amount)
+            offer_resps()
+                .idx
+                .status
+                .prefix(status.key().to_string())
+                .range(deps.storage, start, None, Order::Ascending)
+                .map(|entry| entry.map(|(_id, offer)| offer))
+                .filter(|entry| entry.as_ref().map_or(true, |offer| is_visible(offer, viewer.as_ref())))
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?
+        };
+
+        to_json_binary(&offer_list_resp(offers, limit))
+    }
+
+    pub fn repay_quote(deps: Deps, env: Env, loan_id: u64, at_time: Option<u64>) -> StdResult<Binary> {
+        let Some(offer) = offer_resps().may_load(deps.storage, loan_id)? else {
+            return Err(StdError::GenericErr { msg: "Invalid offer ID".to_string() });
+        };
+        let at_time = at_time.unwrap_or(env.block.time.seconds());
+
+        let quote = exec::repay_quote(deps, &offer, at_time).map_err(|err| StdError::generic_err(err.to_string()))?;
+        to_json_binary(&quote)
+    }
+
+    pub fn simulate_borrow(deps: Deps, offer_id: u64, borrower: Addr, collateral: Vec<Collateral>) -> StdResult<Binary> {
+        let borrower = deps.api.addr_validate(borrower.as_str())?;
+
+        let plan = offer_resps()
+            .may_load(deps.storage, offer_id)?
+            .ok_or(ContractError::OfferNotFound)
+            .and_then(|offer| {
+                exec::check_bundle(&collateral)?;
+                exec::plan_borrow(deps, &borrower, &offer, &collateral)
             });
-        }
 
-        let _total_pages = (resp_offers.len() + page_size as usize - 1) / page_size as usize;
-        let start_index = (page_num - 1) as usize * page_size as usize;
-        let end_index = std::cmp::min(start_index + page_size as usize, resp_offers.len().try_into().unwrap());
-    
-        let result = to_binary(&resp_offers[start_index..end_index])?;
-        Ok(result)
+        let resp = match plan {
+            Ok(plan) => SimulateBorrowResp {
+                error: None,
+                payout: plan.accepted.amount,
+                appraised_value: plan.appraised_value,
+                loan_terms: Some(plan.loan_terms),
+            },
+            Err(err) => SimulateBorrowResp { error: Some(err.to_string()), payout: 0, appraised_value: 0, loan_terms: None },
+        };
+        to_json_binary(&resp)
     }
-    
-    pub fn get_offers_accept_by_borrower(deps: Deps, borrower: Addr, page_size: u16, page_num: u16) -> StdResult<Binary> {
-        let valid_owner = deps.api.addr_validate(borrower.as_str())?;
-        let to_skip_usize = usize::from(page_num * page_size - page_size);
 
-        let offer_data: Vec<_> = offer_resps()
-            .range(deps.storage, None, None, Order::Ascending)
-            .collect::<StdResult<Vec<_>>>()?
-            .into_iter()
-            .skip(to_skip_usize)
-            .take(page_size.into())
-            .filter_map(|entry| {
-                let x = entry.1.clone();
-
-                if x.borrower == valid_owner && x.accepted
-                {
-                   Some(x)
-                } else {
-                   None
-                }
-            })
-            .collect();
-        
-        Ok(to_binary(&offer_data)?)
+    pub fn simulate_lend(deps: Deps, amount: u128, collection_id: u16, token_id: Option<String>, bundle: bool) -> StdResult<Binary> {
+        let offer_id = LAST_OFFER_INDEX.load(deps.storage)? + 1;
+
+        let resp = match exec::plan_lend(deps, collection_id, token_id.as_deref(), bundle) {
+            Ok(max_amount) if max_amount.is_none_or(|max_amount| amount <= max_amount) => SimulateLendResp { error: None, offer_id, max_amount },
+            Ok(max_amount) => SimulateLendResp { error: Some(ContractError::TooMuchLendAmount.to_string()), offer_id, max_amount },
+            Err(err) => SimulateLendResp { error: Some(err.to_string()), offer_id, max_amount: Some(0) },
+        };
+        to_json_binary(&resp)
+    }
+
+    pub fn archived_offer(deps: Deps, offer_id: u64) -> StdResult<Binary> {
+        let Some(archived) = archived_offers().may_load(deps.storage, offer_id)? else {
+            return Err(StdError::GenericErr { msg: "Invalid offer ID".to_string() });
+        };
+
+        to_json_binary(&archived)
+    }
+
+    // Newest first, `start_after` is the last offer id of the previous page
+    fn history_resp(
+        entries: impl Iterator<Item = StdResult<(u64, ArchivedOfferResp)>>,
+        limit: Option<u32>,
+    ) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let offers = entries
+            .map(|entry| entry.map(|(_id, archived)| archived))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let next_start_after = next_start_after(&offers, limit, |archived| archived.offer.offer_id);
+        to_json_binary(&HistoryResp { offers, next_start_after })
+    }
+
+    pub fn history_by_lender(deps: Deps, lender: Addr, start_after: Option<u64>, limit: Option<u32>) -> StdResult<Binary> {
+        let valid_lender = deps.api.addr_validate(lender.as_str())?;
+        let entries = archived_offers()
+            .idx
+            .lender
+            .prefix(valid_lender)
+            .range(deps.storage, None, start_after.map(Bound::exclusive), Order::Descending);
+        history_resp(entries, limit)
+    }
+
+    pub fn history_by_borrower(deps: Deps, borrower: Addr, start_after: Option<u64>, limit: Option<u32>) -> StdResult<Binary> {
+        let valid_borrower = deps.api.addr_validate(borrower.as_str())?;
+        let entries = archived_offers()
+            .idx
+            .borrower
+            .prefix(valid_borrower)
+            .range(deps.storage, None, start_after.map(Bound::exclusive), Order::Descending);
+        history_resp(entries, limit)
+    }
+
+    pub fn history_by_collection(deps: Deps, collection_id: u16, start_after: Option<u64>, limit: Option<u32>) -> StdResult<Binary> {
+        let entries = archived_offers()
+            .idx
+            .collection
+            .prefix(collection_id)
+            .range(deps.storage, None, start_after.map(Bound::exclusive), Order::Descending);
+        history_resp(entries, limit)
+    }
+
+    // open offers reserved for one token, best bid first, ties ordered by id
+    pub fn get_offers_by_token(
+        deps: Deps,
+        collection_id: u16,
+        token_id: String,
+        start_after: Option<PriceCursor>,
+        limit: Option<u32>,
+        viewer: Option<Addr>,
+    ) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let max = start_after.map(|cursor| Bound::exclusive((cursor.amount, cursor.offer_id)));
+
+        let offers = offer_resps()
+            .idx
+            .token
+            .sub_prefix((collection_id, token_id))
+            .range(deps.storage, None, max, Order::Descending)
+            .map(|entry| entry.map(|(_pk, offer)| offer))
+            .filter(|entry| entry.as_ref().map_or(true, |offer| offer.status == OfferStatus::Open && is_visible(offer, viewer.as_ref())))
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let next_start_after = next_start_after(&offers, limit, |offer| PriceCursor { amount: offer.amount, offer_id: offer.offer_id });
+        to_json_binary(&OffersByPriceResp { offers, next_start_after })
     }
 
     pub fn collection_by_id(deps: Deps, collection_id: u16) -> StdResult<Binary> {
         let collection = NFT_COLLECTIONS.load(deps.storage, collection_id)?;
-        let resp_binary = to_binary(&collection)?;
+        let resp_binary = to_json_binary(&collection)?;
         Ok(resp_binary)
     }
 
+    pub fn stats(deps: Deps) -> StdResult<Binary> {
+        let stats = STATS.may_load(deps.storage)?.unwrap_or_default();
+        to_json_binary(&stats)
+    }
+
+    pub fn collection_stats(deps: Deps, collection_id: u16) -> StdResult<Binary> {
+        if !NFT_COLLECTIONS.has(deps.storage, collection_id) {
+            return Err(StdError::not_found("collection"));
+        }
+        let stats = COLLECTION_STATS.may_load(deps.storage, collection_id)?.unwrap_or_default();
+        to_json_binary(&stats)
+    }
+
+    pub fn user_stats(deps: Deps, address: Addr) -> StdResult<Binary> {
+        let as_lender = LENDER_STATS.may_load(deps.storage, &address)?.unwrap_or_default();
+        let as_borrower = BORROWER_STATS.may_load(deps.storage, &address)?.unwrap_or_default();
+        to_json_binary(&UserStatsResp { address, as_lender, as_borrower })
+    }
+
+    pub fn collection_by_contract(deps: Deps, contract: Addr) -> StdResult<Binary> {
+        let collection_id = COLLECTION_BY_CONTRACT.load(deps.storage, &contract)?;
+        collection_by_id(deps, collection_id)
+    }
+
+    // collections ordered by id, with their offer and loan stats
+    pub fn collections(
+        deps: Deps,
+        start_after: Option<u16>,
+        limit: Option<u32>,
+        active_only: Option<bool>,
+        risk_tier: Option<u8>,
+        contract: Option<Addr>,
+    ) -> StdResult<Binary> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let listed: Box<dyn Iterator<Item = StdResult<NFTCollectionResp>>> = match contract {
+            // a contract is listed at most once
+            Some(contract) => {
+                let collection_id = COLLECTION_BY_CONTRACT
+                    .may_load(deps.storage, &contract)?
+                    .filter(|collection_id| start_after.is_none_or(|start_after| *collection_id > start_after));
+                Box::new(collection_id.map(|collection_id| NFT_COLLECTIONS.load(deps.storage, collection_id)).into_iter())
+            },
+            None => Box::new(
+                NFT_COLLECTIONS
+                    .range(deps.storage, start, None, Order::Ascending)
+                    .map(|entry| entry.map(|(_id, collection)| collection)),
+            ),
+        };
+
+        let collections = listed
+            .filter(|entry| {
+                entry.as_ref().map_or(true, |collection| {
+                    !(active_only.unwrap_or(false) && collection.disabled)
+                        && risk_tier.is_none_or(|risk_tier| collection.risk_tier == risk_tier)
+                })
+            })
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let next_start_after = next_start_after(&collections, limit, |collection| collection.collection_id);
+        let nftcollections = collections
+            .into_iter()
+            .map(|collection| collection_summary(deps, collection))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        to_json_binary(&NFTCollectionListResp { nftcollections, next_start_after })
+    }
+
+    fn collection_summary(deps: Deps, collection: NFTCollectionResp) -> StdResult<CollectionSummaryResp> {
+        let stats = COLLECTION_STATS.may_load(deps.storage, collection.collection_id)?.unwrap_or_default();
+
+        // first public open offer from the top of the order book
+        let mut best_offer: Option<u128> = None;
+        for entry in offer_resps()
+            .idx
+            .book
+            .sub_prefix((collection.collection_id, OfferStatus::Open.key().to_string()))
+            .range(deps.storage, None, None, Order::Descending)
+        {
+            let (_id, offer) = entry?;
+            if is_visible(&offer, None) {
+                best_offer = Some(offer.amount);
+                break;
+            }
+        }
+
+        Ok(CollectionSummaryResp { collection, stats, best_offer })
+    }
+
     pub fn query_admin(deps: Deps) -> StdResult<Binary> {
         let admin = CONFIG.load(deps.storage)?;
         let resp = ContractConfig { admin: admin.clone().admin, interest: admin.clone().interest };
-        let resp_binary = to_binary(&resp)?;
+        let resp_binary = to_json_binary(&resp)?;
         Ok(resp_binary)
     }
+
+    pub fn config(deps: Deps) -> StdResult<Binary> {
+        let config = CONFIG.load(deps.storage)?;
+        let resp = ConfigResp {
+            admin: config.admin,
+            pending_admin: PENDING_ADMIN.may_load(deps.storage)?,
+            interest: config.interest,
+            accrued_fees: ACCRUED_FEES.may_load(deps.storage)?.unwrap_or_default(),
+            denom: LEND_DENOM.load(deps.storage)?,
+            last_offer_id: LAST_OFFER_INDEX.load(deps.storage)?,
+            last_collection_id: LAST_COLLECTION_ID.load(deps.storage)?,
+            note_contract: NOTE_CONTRACT.may_load(deps.storage)?,
+            receipt_contract: RECEIPT_CONTRACT.may_load(deps.storage)?,
+            pause: pause_state_resp(deps)?,
+        };
+        to_json_binary(&resp)
+    }
+
+    pub fn pending_admin(deps: Deps) -> StdResult<Binary> {
+        let pending_admin = PENDING_ADMIN.may_load(deps.storage)?;
+        to_json_binary(&pending_admin)
+    }
+
+    // roles held by an address, all of them for the owner
+    pub fn roles(deps: Deps, address: Addr) -> StdResult<Binary> {
+        let mut roles: Vec<Role> = vec![];
+        for role in Role::ALL {
+            if exec::has_role(deps, &address, role)? {
+                roles.push(role);
+            }
+        }
+        to_json_binary(&roles)
+    }
+
+    pub fn accrued_fees(deps: Deps) -> StdResult<Binary> {
+        let accrued_fees = ACCRUED_FEES.may_load(deps.storage)?.unwrap_or_default();
+        to_json_binary(&accrued_fees)
+    }
+
+    pub fn pause_state(deps: Deps) -> StdResult<Binary> {
+        to_json_binary(&pause_state_resp(deps)?)
+    }
+
+    fn pause_state_resp(deps: Deps) -> StdResult<PauseStateResp> {
+        let global = Operation::ALL
+            .into_iter()
+            .filter(|operation| PAUSED_OPERATIONS.has(deps.storage, operation.key()))
+            .collect();
+
+        let mut collections: Vec<CollectionPauseResp> = vec![];
+        for collection_id in NFT_COLLECTIONS.keys(deps.storage, None, None, Order::Ascending) {
+            let collection_id = collection_id?;
+            let operations: Vec<Operation> = Operation::ALL
+                .into_iter()
+                .filter(|operation| PAUSED_COLLECTION_OPERATIONS.has(deps.storage, (collection_id, operation.key())))
+                .collect();
+            if !operations.is_empty() {
+                collections.push(CollectionPauseResp { collection_id, operations });
+            }
+        }
+
+        let settings = PAUSE_SETTINGS.may_load(deps.storage)?.unwrap_or_default();
+        Ok(PauseStateResp { global, collections, settings })
+    }
+
+    pub fn note_contract(deps: Deps) -> StdResult<Binary> {
+        let note_contract = NOTE_CONTRACT.may_load(deps.storage)?;
+        to_json_binary(&note_contract)
+    }
+
+    // Open offers have no position yet
+    pub fn position_status(deps: Deps, offer_id: u64) -> StdResult<Binary> {
+        let resp = match offer_resps().may_load(deps.storage, offer_id)? {
+            Some(offer) if offer.status == OfferStatus::Active => PositionStatusResp { offer_id, live: true, outcome: None },
+            Some(_offer) => return Err(StdError::not_found("loan")),
+            None => match archived_offers().may_load(deps.storage, offer_id)? {
+                Some(archived) if archived.offer.borrower.is_some() => PositionStatusResp { offer_id, live: false, outcome: Some(archived.outcome) },
+                _ => return Err(StdError::not_found("loan")),
+            },
+        };
+        to_json_binary(&resp)
+    }
+
+    pub fn receipt_contract(deps: Deps) -> StdResult<Binary> {
+        let receipt_contract = RECEIPT_CONTRACT.may_load(deps.storage)?;
+        to_json_binary(&receipt_contract)
+    }
+
+    pub fn appraised_value(deps: Deps, collection_id: u16, token_id: String) -> StdResult<Binary> {
+        let collection = NFT_COLLECTIONS.load(deps.storage, collection_id)?;
+        let appraisal = appraise(deps, &collection, &token_id)?;
+        to_json_binary(&appraisal)
+    }
  }
 
 
@@ -45,4 +45,79 @@ pub enum ContractError {
     #[error("Offer Not accepted")]
     OfferNotAccepted,
 
+    #[error("Offer is closed")]
+    OfferClosed,
+
+    #[error("Loan amount exceeds appraised value of the NFT")]
+    ExceedsAppraisedValue,
+
+    #[error("Invalid appraisal multiplier")]
+    InvalidMultiplier,
+
+    #[error("NFT does not match the trait filter of the offer")]
+    TraitMismatch,
+
+    #[error("Offer is reserved for another token")]
+    TokenMismatch,
+
+    #[error("Invalid collateral bundle")]
+    InvalidBundle,
+
+    #[error("Offer only accepts a single NFT of its collection")]
+    NotBundleOffer,
+
+    #[error("Note and receipt contracts must be cw721 minted by this contract")]
+    InvalidMinterContract,
+
+    #[error("Recipient was not authorized by the borrower")]
+    UnauthorizedRecipient,
+
+    #[error("Loan is past its due date")]
+    LoanExpired,
+
+    #[error("Loan is not past its due date")]
+    LoanNotExpired,
+
+    #[error("Payment is below the {due} due")]
+    InsufficientPayment { due: u128 },
+
+    #[error("Amount due {due} exceeds the max payment {max_payment}")]
+    MaxPaymentExceeded { due: u128, max_payment: u128 },
+
+    #[error("No admin transfer pending")]
+    NoPendingAdmin,
+
+    #[error("Invalid fee amount")]
+    InvalidFeeAmount,
+
+    #[error("Lender interest share must be at most 100 percent")]
+    InvalidInterest,
+
+    #[error("Operation {operation} is paused")]
+    Paused { operation: String },
+
+    #[error("Operation {operation} stays open during pauses")]
+    StaysOpenDuringPause { operation: String },
+
+    #[error("Collection already exists")]
+    CollectionAlreadyExists,
+
+    #[error("Collection is disabled")]
+    CollectionDisabled,
+
+    #[error("Collection has outstanding loans")]
+    CollectionInUse,
+
+    #[error("Not a cw721 contract")]
+    InvalidNftContract,
+
+    #[error("{0}")]
+    SemVer(#[from] semver::Error),
+
+    #[error("Cannot migrate from contract {contract}")]
+    WrongContract { contract: String },
+
+    #[error("Cannot migrate from version {from} to older version {to}")]
+    CannotDowngrade { from: String, to: String },
+
 }
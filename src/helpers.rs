@@ -1,9 +1,11 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use cosmwasm_std::{
-    to_binary, Addr, CosmosMsg, StdResult, WasmMsg, Coin,
+    to_json_binary, Addr, CosmosMsg, Deps, StdResult, WasmMsg, Coin,
 };
-use crate::msg::{ExecuteMsg};
+use cw721::{ContractInfoResponse, Cw721QueryMsg, NftInfoResponse, OwnerOfResponse};
+use crate::msg::{ExecuteMsg, AppraisalResp, NFTCollectionResp, NftMetadata, Trait};
+use crate::state::{ TOKEN_APPRAISALS, TRAIT_APPRAISALS };
 /// CwTemplateContract is a wrapper around Addr that provides a lot of helpers
 /// for working with this.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -13,7 +15,7 @@ impl CwTemplateContract {
         self.0.clone()
     }
     pub fn call<T: Into<ExecuteMsg>>(&self, msg: T, funds: Coin) -> StdResult<CosmosMsg> {
-        let msg = to_binary(&msg.into())?;
+        let msg = to_json_binary(&msg.into())?;
         Ok(WasmMsg::Execute {
             contract_addr: self.addr().into(),
             msg,
@@ -21,5 +23,59 @@ impl CwTemplateContract {
         }
         .into())
     }
-   
-}
\ No newline at end of file
+
+}
+
+/// Multiplier applied to the floor price when nothing overrides it.
+pub const DEFAULT_MULTIPLIER: u16 = 100;
+
+/// Reads the traits of a token from the `NftInfo` metadata extension.
+/// Collections without `Metadata`-style attributes simply have no traits.
+pub fn query_nft_traits(deps: Deps, nft_contract: &Addr, token_id: &str) -> Vec<Trait> {
+    let query = Cw721QueryMsg::NftInfo { token_id: token_id.to_string() };
+
+    match deps.querier.query_wasm_smart::<NftInfoResponse<Option<NftMetadata>>>(nft_contract, &query) {
+        Ok(info) => info.extension.and_then(|metadata| metadata.attributes).unwrap_or_default(),
+        Err(_err) => vec![],
+    }
+}
+
+/// Returns the current holder of a token.
+pub fn query_nft_owner(deps: Deps, nft_contract: &Addr, token_id: &str) -> StdResult<String> {
+    let query = Cw721QueryMsg::OwnerOf { token_id: token_id.to_string(), include_expired: None };
+    let resp: OwnerOfResponse = deps.querier.query_wasm_smart(nft_contract, &query)?;
+    Ok(resp.owner)
+}
+
+/// Returns the cw721 name and symbol of an NFT contract.
+pub fn query_nft_contract_info(deps: Deps, nft_contract: &Addr) -> StdResult<ContractInfoResponse> {
+    deps.querier.query_wasm_smart(nft_contract, &Cw721QueryMsg::ContractInfo {})
+}
+
+/// Appraises a token: a per-token override wins, otherwise the highest
+/// multiplier of its matching traits, otherwise the floor price.
+pub fn appraise(deps: Deps, collection: &NFTCollectionResp, token_id: &str) -> StdResult<AppraisalResp> {
+    let collection_id = collection.collection_id;
+
+    let multiplier = match TOKEN_APPRAISALS.may_load(deps.storage, (collection_id, token_id))? {
+        Some(multiplier) => multiplier,
+        None => {
+            let mut best: Option<u16> = None;
+            for token_trait in query_nft_traits(deps, &collection.contract, token_id) {
+                let key = (collection_id, token_trait.trait_type.as_str(), token_trait.value.as_str());
+                if let Some(multiplier) = TRAIT_APPRAISALS.may_load(deps.storage, key)? {
+                    best = Some(best.map_or(multiplier, |current| current.max(multiplier)));
+                }
+            }
+            best.unwrap_or(DEFAULT_MULTIPLIER)
+        }
+    };
+
+    Ok(AppraisalResp {
+        collection_id,
+        token_id: token_id.to_string(),
+        floor_price: collection.floor_price,
+        multiplier,
+        value: collection.floor_price * multiplier as u128 / 100,
+    })
+}
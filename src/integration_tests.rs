@@ -2,9 +2,11 @@
 mod tests {
     use crate::helpers::CwTemplateContract;
     use crate::msg::InstantiateMsg;
-    use crate::msg::NFTCollectionResp;
+    use crate::msg::{NFTCollectionMsg, NFTCollectionResp, NftMetadata, Trait};
     use crate::error::*;
-    use cosmwasm_std::{Addr, Coin, Empty, Uint128, coins, Timestamp, BlockInfo};
+        use cw_storage_plus::Map;
+    use cosmwasm_std::{Addr, Binary, Coin, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult, Uint128, Timestamp, BlockInfo, Event};
+    use cw721_base::Cw721Contract;
     use cw_multi_test::{App, AppBuilder, Contract, ContractWrapper, Executor};
     
     pub fn contract_template() -> Box<dyn Contract<Empty>> {
@@ -12,12 +14,118 @@ mod tests {
             crate::contract::execute,
             crate::contract::instantiate,
             crate::contract::query,
+        )
+        .with_migrate(crate::contract::migrate);
+        Box::new(contract)
+    }
+
+    // The current code with the first release layout: offers keyed by (owner, u16 id),
+    // caller chosen collection ids and no cw2 version info. Offers and collection 1 are
+    // stored exactly as the first release wrote them.
+    fn legacy_instantiate(mut deps: DepsMut, env: Env, info: MessageInfo, msg: InstantiateMsg) -> Result<Response, ContractError> {
+        let start_time = env.block.time.seconds();
+        let res = crate::contract::instantiate(deps.branch(), env, info, msg)?;
+        cw2::CONTRACT.remove(deps.storage);
+        crate::state::LAST_COLLECTION_ID.remove(deps.storage);
+        crate::state::COLLECTION_BY_CONTRACT.clear(deps.storage);
+
+        // an open offer and a loan, which only knew its token
+        let offers = Map::<(&Addr, u16), serde_json::Value>::new("offers_im");
+        for (offer_id, owner, token_id, borrower) in [(1, USER, "", "none"), (2, ANOTHER_USER, "escrowed", BORROWER)] {
+            let owner = Addr::unchecked(owner);
+            offers.save(
+                deps.storage,
+                (&owner, offer_id),
+                &serde_json::json!({
+                    "offer_id": offer_id,
+                    "owner": owner,
+                    // u128 amounts are written as strings
+                    "amount": "50",
+                    "start_time": start_time,
+                    "collection_id": 1,
+                    "token_id": token_id,
+                    "accepted": borrower != "none",
+                    "borrower": borrower,
+                }),
+            )?;
+        }
+        crate::state::LAST_OFFER_INDEX.save(deps.storage, &2)?;
+
+        let collection = crate::state::NFT_COLLECTIONS.load(deps.storage, 1)?;
+        Map::<u16, serde_json::Value>::new("nft_collections").save(
+            deps.storage,
+            1,
+            &serde_json::json!({
+                "collection_id": 1,
+                "collection": collection.collection,
+                "floor_price": collection.floor_price.to_string(),
+                "contract": collection.contract,
+                "apy": collection.apy,
+                "max_time": collection.max_time,
+            }),
+        )?;
+
+        Ok(res)
+    }
+
+    pub fn contract_legacy() -> Box<dyn Contract<Empty>> {
+        let contract = ContractWrapper::new(
+            crate::contract::execute,
+            legacy_instantiate,
+            crate::contract::query,
+        );
+        Box::new(contract)
+    }
+
+    // The current code recorded under another cw2 name or version
+    fn stamped_instantiate(mut deps: DepsMut, env: Env, info: MessageInfo, msg: InstantiateMsg, contract: &str, version: &str) -> Result<Response, ContractError> {
+        let res = crate::contract::instantiate(deps.branch(), env, info, msg)?;
+        cw2::set_contract_version(deps.storage, contract, version)?;
+        Ok(res)
+    }
+
+    fn newer_instantiate(deps: DepsMut, env: Env, info: MessageInfo, msg: InstantiateMsg) -> Result<Response, ContractError> {
+        stamped_instantiate(deps, env, info, msg, "crates.io:foxy-lend", "99.0.0")
+    }
+
+    fn foreign_instantiate(deps: DepsMut, env: Env, info: MessageInfo, msg: InstantiateMsg) -> Result<Response, ContractError> {
+        stamped_instantiate(deps, env, info, msg, "crates.io:other-lend", "0.2.0")
+    }
+
+    pub fn contract_stamped(newer: bool) -> Box<dyn Contract<Empty>> {
+        let instantiate = if newer { newer_instantiate } else { foreign_instantiate };
+        let contract = ContractWrapper::new(
+            crate::contract::execute,
+            instantiate,
+            crate::contract::query,
         );
         Box::new(contract)
     }
 
+    // cw721-base with a `Metadata`-style extension so tokens can carry traits
+    type NftExtension = Option<NftMetadata>;
+    type NftContract<'a> = Cw721Contract<'a, NftExtension, Empty, Empty, Empty>;
+
+    fn nft_execute(deps: DepsMut, env: Env, info: MessageInfo, msg: cw721_base::ExecuteMsg<NftExtension, Empty>) -> Result<Response, cw721_base::ContractError> {
+        NftContract::default().execute(deps, env, info, msg)
+    }
+
+    fn nft_instantiate(deps: DepsMut, env: Env, info: MessageInfo, msg: cw721_base::InstantiateMsg) -> StdResult<Response> {
+        NftContract::default().instantiate(deps, env, info, msg)
+    }
+
+    fn nft_query(deps: Deps, env: Env, msg: cw721_base::QueryMsg<Empty>) -> StdResult<Binary> {
+        NftContract::default().query(deps, env, msg)
+    }
+
+    pub fn contract_cw721() -> Box<dyn Contract<Empty>> {
+        let contract = ContractWrapper::new(nft_execute, nft_instantiate, nft_query);
+        Box::new(contract)
+    }
+
     const USER: &str = "user";
     const ANOTHER_USER: &str = "another_user";
+    const BORROWER: &str = "borrow";
     const ADMIN: &str = "admin";
     const DENOM: &str = "SEI";
     const INTEREST: u128 = 80;
@@ -71,23 +179,28 @@ mod tests {
     fn proper_instantiate() -> (App, CwTemplateContract) {
         let mut app = mock_app();
         let cw_template_id = app.store_code(contract_template());
+        let cw721_id = app.store_code(contract_cw721());
 
+        let nft_contract1 = instantiate_nft(&mut app, cw721_id, "Collection1");
+        let nft_contract2 = instantiate_nft(&mut app, cw721_id, "Collection2");
+
+        // listed as collections 1 and 2
         let nft_collections = vec![
-            NFTCollectionResp {
-                collection_id: 1,
+            NFTCollectionMsg {
                 collection: "Collection1".to_string(),
                 floor_price: 100,
-                contract: Addr::unchecked("contract1"),
+                contract: nft_contract1.clone(),
                 apy: 5,
                 max_time: 3600 * 24 * 365,
+                risk_tier: 0,
             },
-            NFTCollectionResp {
-                collection_id: 2,
+            NFTCollectionMsg {
                 collection: "Collection2".to_string(),
                 floor_price: 150,
-                contract: Addr::unchecked("contract2"),
+                contract: nft_contract2.clone(),
                 apy: 7,
                 max_time: 130,
+                risk_tier: 0,
             },
         ];
 
@@ -95,7 +208,7 @@ mod tests {
         let msg = InstantiateMsg {
             nft_collections: nft_collections.clone(),
             admin: Addr::unchecked(ADMIN).clone(),
-            interest: INTEREST.clone(),
+            interest: INTEREST,
         };
 
         let cw_template_contract_addr = app
@@ -109,12 +222,116 @@ mod tests {
             )
             .unwrap();
         let cw_template_contract = CwTemplateContract(cw_template_contract_addr);
+
+        // The borrower holds a couple of tokens in each collection and lets the contract move them
+        for nft_contract in [&nft_contract1, &nft_contract2] {
+            for token_id in ["token123", "token13"] {
+                mint_nft(&mut app, nft_contract, token_id, BORROWER, None);
+            }
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                nft_contract.clone(),
+                &cw721_base::ExecuteMsg::<NftExtension, Empty>::ApproveAll {
+                    operator: cw_template_contract.addr().to_string(),
+                    expires: None,
+                },
+                &[],
+            ).unwrap();
+        }
+
         (app, cw_template_contract)
     }
 
+    fn instantiate_nft(app: &mut App, code_id: u64, name: &str) -> Addr {
+        app.instantiate_contract(
+            code_id,
+            Addr::unchecked(ADMIN),
+            &cw721_base::InstantiateMsg {
+                name: name.to_string(),
+                symbol: "NFT".to_string(),
+                minter: ADMIN.to_string(),
+            },
+            &[],
+            name,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn mint_nft(app: &mut App, nft_contract: &Addr, token_id: &str, owner: &str, attributes: Option<Vec<Trait>>) {
+        app.execute_contract(
+            Addr::unchecked(ADMIN),
+            nft_contract.clone(),
+            &cw721_base::ExecuteMsg::<NftExtension, Empty>::Mint {
+                token_id: token_id.to_string(),
+                owner: owner.to_string(),
+                token_uri: None,
+                extension: Some(NftMetadata { attributes }),
+            },
+            &[],
+        ).unwrap();
+    }
+
+    // cw721 minted by the lending contract itself, used for notes and receipts
+    fn instantiate_position_contract(app: &mut App, cw_template_contract: &CwTemplateContract, name: &str) -> Addr {
+        let code_id = app.store_code(contract_cw721());
+        app.instantiate_contract(
+            code_id,
+            Addr::unchecked(ADMIN),
+            &cw721_base::InstantiateMsg {
+                name: name.to_string(),
+                symbol: "POS".to_string(),
+                minter: cw_template_contract.addr().to_string(),
+            },
+            &[],
+            name,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn collection_contract(app: &App, cw_template_contract: &CwTemplateContract, collection_id: u16) -> Addr {
+        let collection: NFTCollectionResp = app
+            .wrap()
+            .query_wasm_smart(cw_template_contract.addr(), &crate::msg::QueryMsg::CollectionByID { collection_id })
+            .unwrap();
+        collection.contract
+    }
+
+    fn nft_owner(app: &App, nft_contract: &Addr, token_id: &str) -> String {
+        let resp: cw721::OwnerOfResponse = app
+            .wrap()
+            .query_wasm_smart(nft_contract, &cw721::Cw721QueryMsg::OwnerOf { token_id: token_id.to_string(), include_expired: None })
+            .unwrap();
+        resp.owner
+    }
+
+    // Listing message of an existing collection
+    fn listing(collection: NFTCollectionResp) -> NFTCollectionMsg {
+        NFTCollectionMsg {
+            collection: collection.collection,
+            floor_price: collection.floor_price,
+            contract: collection.contract,
+            apy: collection.apy,
+            max_time: collection.max_time,
+            risk_tier: collection.risk_tier,
+        }
+    }
+
+    // Instantiates `code_id` with collection 1 of the fixture and ADMIN as contract admin
+    fn instantiate_migratable(app: &mut App, code_id: u64, cw_template_contract: &CwTemplateContract) -> Addr {
+        let collection: NFTCollectionResp = app
+            .wrap()
+            .query_wasm_smart(cw_template_contract.addr(), &crate::msg::QueryMsg::CollectionByID { collection_id: 1 })
+            .unwrap();
+        let msg = InstantiateMsg { nft_collections: vec![listing(collection)], admin: Addr::unchecked(ADMIN), interest: INTEREST };
+        app.instantiate_contract(code_id, Addr::unchecked(ADMIN), &msg, &[], "Migratable", Some(ADMIN.to_string()))
+            .unwrap()
+    }
+
     mod execute {
         use super::*;
-        use crate::msg::{ExecuteMsg, QueryMsg, OfferResp, OfferListResp, ContractConfig, NFTCollectionResp };
+        use crate::msg::{ExecuteMsg, MigrateMsg, QueryMsg, OfferResp, OfferListResp, OffersByPriceResp, PriceCursor, OfferStatus, ArchivedOfferResp, HistoryResp, LoansDueResp, LoanTerms, PositionStatusResp, ContractConfig, NFTCollectionResp, AppraisalResp, TraitFilter, Collateral, Role, Operation, PauseStateResp, CollectionPauseResp, NFTCollectionListResp, LoanStats };
 
         #[test]
         fn lend() {
@@ -124,7 +341,7 @@ mod tests {
 
             let collection_id: u16 = 1;
 
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(50u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -143,8 +360,16 @@ mod tests {
                     start_time: resp.start_time,
                     collection_id: 1,
                     token_id: "".to_string(),
-                    accepted: false,
-                    borrower: Addr::unchecked("none")
+                    status: OfferStatus::Open,
+                    borrower: None,
+                    trait_filter: None,
+                    bundle: false,
+                    collateral: vec![],
+                    allowed_borrowers: None,
+                    note_contract: None,
+                    receipt_contract: None,
+                    repay_recipient: None,
+                    loan_terms: None,
                 }
             );
         }
@@ -155,9 +380,9 @@ mod tests {
             // Set amount and collection id to make offer
             let amount: u128 = 50;
             let collection_id: u16 = 1;
-            let offer_id: u16 = 1;
+            let offer_id: u64 = 1;
 
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(50u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -173,7 +398,7 @@ mod tests {
             app.execute_contract(
                 Addr::unchecked("user"),
                 cw_template_contract.addr().clone(),
-                &ExecuteMsg::CancelOffer { offer_id: offer_id },
+                &ExecuteMsg::CancelOffer { offer_id },
                 &[]
             ).unwrap();
 
@@ -185,6 +410,7 @@ mod tests {
         #[test]
         fn test_update_floor_price() {
             let (mut app, cw_template_contract) = proper_instantiate();
+            let nft_contract = collection_contract(&app, &cw_template_contract, 1);
 
             app.execute_contract(
                 Addr::unchecked("admin"),
@@ -204,9 +430,11 @@ mod tests {
                     collection_id: 1,
                     collection: "Collection1".to_string(),
                     floor_price: 120,
-                    contract: Addr::unchecked("contract1"),
+                    contract: nft_contract,
                     apy: 5,
-                    max_time: 31536000
+                    max_time: 31536000,
+                    risk_tier: 0,
+                    disabled: false,
                 }
             );
         }
@@ -214,12 +442,26 @@ mod tests {
         #[test]
         fn test_add_new_admin() {
             let (mut app, cw_template_contract) = proper_instantiate();
-            let new_admin = Addr::unchecked("UpdateAdmin");
+            let new_admin = Addr::unchecked("new_admin");
 
             app.execute_contract(
                 Addr::unchecked("admin"),
                 cw_template_contract.addr().clone(),
-                &ExecuteMsg::UpdateAdmin {new_admin: new_admin.clone() },
+                &ExecuteMsg::ProposeAdmin {new_admin: new_admin.clone() },
+                &[],
+            ).unwrap();
+
+            // the current admin stays in charge until the proposal is accepted
+            let pending_admin: Option<Addr> = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::PendingAdmin {})
+                .unwrap();
+            assert_eq!(pending_admin, Some(new_admin.clone()));
+
+            app.execute_contract(
+                new_admin.clone(),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::AcceptAdmin {},
                 &[],
             ).unwrap();
 
@@ -231,7 +473,7 @@ mod tests {
             assert_eq!(
                 resp,
                 ContractConfig {
-                    admin: Addr::unchecked("UpdateAdmin"),
+                    admin: Addr::unchecked("new_admin"),
                     interest: 80,
                 }
             );
@@ -270,7 +512,7 @@ mod tests {
             let collection_id: u16 = 1;
 
             // create the offer
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(50u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -281,7 +523,7 @@ mod tests {
             app.execute_contract(
                 Addr::unchecked("borrow"),
                 cw_template_contract.addr().clone(),
-                &ExecuteMsg::Borrow {owner: Addr::unchecked("user"),offer_id: 1, token_id: token_id.clone() },
+                &ExecuteMsg::Borrow {offer_id: 1, token_id: token_id.clone() },
                 &[],
             ).unwrap();
 
@@ -291,6 +533,9 @@ mod tests {
                 .unwrap();
 
             println!("{:?}", resp);
+            // the NFT is held in escrow by the contract
+            let nft_contract = collection_contract(&app, &cw_template_contract, collection_id);
+            assert_eq!(nft_owner(&app, &nft_contract, &token_id), cw_template_contract.addr().to_string());
         }
 
         #[test]
@@ -301,7 +546,7 @@ mod tests {
             let collection_id: u16 = 1;
 
             // create the offer
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(50u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -312,7 +557,7 @@ mod tests {
             app.execute_contract(
                 Addr::unchecked("borrow"),
                 cw_template_contract.addr().clone(),
-                &ExecuteMsg::Borrow {owner: Addr::unchecked("user"),offer_id: 1, token_id: token_id.clone() },
+                &ExecuteMsg::Borrow {offer_id: 1, token_id: token_id.clone() },
                 &[],
             ).unwrap();
 
@@ -333,192 +578,1588 @@ mod tests {
                 chain_id: block.chain_id,
             });
             // repay function
-            let msg = ExecuteMsg::Repay {owner: Addr::unchecked("user"),offer_id: 1 } ;
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: None } ;
             let funds_sent = Coin::new(173u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             let res = app.execute(Addr::unchecked("borrow"), cosmos_msg).unwrap(); 
 
             println!("{:?}", res);
+            // the NFT is back with the borrower
+            let nft_contract = collection_contract(&app, &cw_template_contract, collection_id);
+            assert_eq!(nft_owner(&app, &nft_contract, &token_id), BORROWER);
         }
-    }
-
-    mod execute_fail {
-        use super::*;
-        use crate::msg::{ExecuteMsg, QueryMsg, OfferResp, OfferListResp, ContractConfig, NFTCollectionResp };
 
         #[test]
-        fn lend_fail_over_floor_price() {
+        fn appraisal_by_trait_and_token() {
             let (mut app, cw_template_contract) = proper_instantiate();
-            // Set amount and collection id to make offer
-            let amount: u128 = 200;
-            let collection_id: u16 = 1;
-            // the floor price of collection id  = 1 is 100
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
-            let funds_sent = Coin::new(200u128, "SEI".to_string());
-            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
-            app.execute(Addr::unchecked(USER), cosmos_msg); 
+            let nft_contract = collection_contract(&app, &cw_template_contract, 1);
+            let gold = Trait { display_type: None, trait_type: "Background".to_string(), value: "Gold".to_string() };
+            mint_nft(&mut app, &nft_contract, "gold1", BORROWER, Some(vec![gold]));
+
+            // admin hands appraisals over to a dedicated appraiser
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::GrantRole { role: Role::Appraiser, address: Addr::unchecked("appraiser") },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked("appraiser"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::SetTraitAppraisal { collection_id: 1, trait_type: "Background".to_string(), value: "Gold".to_string(), multiplier: Some(300) },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked("appraiser"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::SetTokenAppraisal { collection_id: 1, token_id: "token123".to_string(), multiplier: Some(150) },
+                &[],
+            ).unwrap();
+
+            let appraised = |app: &App, token_id: &str| -> AppraisalResp {
+                app.wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::AppraisedValue { collection_id: 1, token_id: token_id.to_string() })
+                    .unwrap()
+            };
+            assert_eq!(appraised(&app, "gold1").value, 300);
+            assert_eq!(appraised(&app, "token123").value, 150);
+            // no traits and no override: floor price
+            assert_eq!(appraised(&app, "token13").value, 100);
+            assert_eq!(appraised(&app, "token13").multiplier, 100);
         }
 
         #[test]
-        fn cancel_offer_fail_by_another() {
+        fn borrow_with_trait_filter() {
             let (mut app, cw_template_contract) = proper_instantiate();
-            // Set amount and collection id to make offer
-            let amount: u128 = 50;
-            let collection_id: u16 = 1;
-            let offer_id: u16 = 1;
+            let nft_contract = collection_contract(&app, &cw_template_contract, 1);
+            let gold = Trait { display_type: None, trait_type: "Background".to_string(), value: "Gold".to_string() };
+            mint_nft(&mut app, &nft_contract, "gold1", BORROWER, Some(vec![gold]));
 
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
-            let funds_sent = Coin::new(50u128, "SEI".to_string());
-            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
-            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
-            // create offer
-            let _res: OfferResp = app
-                .wrap()
-                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID {offer_id: 1})
-                .unwrap();
+            // only NFTs with a gold background qualify
+            let trait_filter = vec![TraitFilter { trait_type: "Background".to_string(), value: "Gold".to_string() }];
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: Some(trait_filter.clone()), bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
 
-            // cancel offer
             app.execute_contract(
-                Addr::unchecked("another_user"),
-                cw_template_contract.addr().clone(),
-                &ExecuteMsg::CancelOffer { offer_id: offer_id },
-                &[]
-            );
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "gold1".to_string() },
+                &[],
+            ).unwrap();
+
+            let resp: OfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID { offer_id: 1 })
+                .unwrap();
+            assert_eq!(resp.status, OfferStatus::Active);
+            assert_eq!(resp.trait_filter, Some(trait_filter));
+            assert_eq!(nft_owner(&app, &nft_contract, "gold1"), cw_template_contract.addr().to_string());
         }
-        
+
         #[test]
-        fn borrow_fail_about_accpeted_offer() {
+        fn token_specific_offer() {
             let (mut app, cw_template_contract) = proper_instantiate();
-            // Set amount and collection id to make offer
-            let amount: u128 = 50;
-            let collection_id: u16 = 1;
 
-            // create the offer
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
-            let funds_sent = Coin::new(50u128, "SEI".to_string());
-            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
-            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
-            
-            // borrow nft
-            let token_id = "token123".to_string();
+            // two bids on token13, one collection-wide offer and a private bid for the borrower
+            for (amount, token_id, allowed_borrowers) in [
+                (60u128, Some("token13"), None),
+                (80, Some("token13"), None),
+                (90, None, None),
+                (100, Some("token13"), Some(vec![Addr::unchecked(BORROWER)])),
+            ] {
+                let msg = ExecuteMsg::Lend { amount, collection_id: 1, token_id: token_id.map(str::to_string), trait_filter: None, bundle: false, allowed_borrowers };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(amount, "SEI".to_string())).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+
+            let bids = |app: &App, start_after: Option<PriceCursor>, limit: Option<u32>, viewer: Option<&str>| -> (Vec<u64>, Option<PriceCursor>) {
+                let resp: OffersByPriceResp = app
+                    .wrap()
+                    .query_wasm_smart(
+                        cw_template_contract.addr(),
+                        &QueryMsg::OffersByToken { collection_id: 1, token_id: "token13".to_string(), start_after, limit, viewer: viewer.map(Addr::unchecked) },
+                    )
+                    .unwrap();
+                (resp.offers.iter().map(|offer| offer.offer_id).collect(), resp.next_start_after)
+            };
+            // best bid first, the private bid is only listed for its borrower
+            assert_eq!(bids(&app, None, None, None).0, vec![2, 1]);
+            assert_eq!(bids(&app, None, None, Some(BORROWER)).0, vec![4, 2, 1]);
+            assert_eq!(bids(&app, None, Some(1), None), (vec![2], Some(PriceCursor { amount: 80, offer_id: 2 })));
+            assert_eq!(bids(&app, Some(PriceCursor { amount: 100, offer_id: 4 }), None, Some(BORROWER)).0, vec![2, 1]);
+            let listed: NFTCollectionListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Collections { start_after: None, limit: None, active_only: None, risk_tier: None, contract: None })
+                .unwrap();
+            assert_eq!(listed.nftcollections[0].best_offer, Some(90));
 
             app.execute_contract(
-                Addr::unchecked("borrow"),
-                cw_template_contract.addr().clone(),
-                &ExecuteMsg::Borrow {owner: Addr::unchecked("user"), offer_id: 1, token_id: token_id.clone() },
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 2, token_id: "token13".to_string() },
                 &[],
             ).unwrap();
-            // fail because this was already accepted
+
+            assert_eq!(bids(&app, None, None, None).0, vec![1]);
+        }
+
+        #[test]
+        fn borrow_bundle_and_repay() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            // a bundle offer may exceed the floor price of the collection
+            let msg = ExecuteMsg::Lend { amount: 250, collection_id: 1, token_id: None, trait_filter: None, bundle: true, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(250u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // 100 + 100 + 150 of floor value across both collections
+            let collateral = vec![
+                Collateral { collection_id: 1, token_id: "token123".to_string() },
+                Collateral { collection_id: 1, token_id: "token13".to_string() },
+                Collateral { collection_id: 2, token_id: "token123".to_string() },
+            ];
             app.execute_contract(
-                Addr::unchecked("another_user"),
-                cw_template_contract.addr().clone(),
-                &ExecuteMsg::Borrow {owner: Addr::unchecked("user"),offer_id: 1, token_id: token_id.clone() },
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::BorrowBundle { offer_id: 1, collateral: collateral.clone() },
                 &[],
-            );
+            ).unwrap();
+
+            let resp: OfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID { offer_id: 1 })
+                .unwrap();
+            assert_eq!(resp.collateral, collateral);
+            for item in &collateral {
+                let nft_contract = collection_contract(&app, &cw_template_contract, item.collection_id);
+                assert_eq!(nft_owner(&app, &nft_contract, &item.token_id), cw_template_contract.addr().to_string());
+            }
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 30),
+                chain_id: block.chain_id,
+            });
+            // 250 * 30 days * 5 / 365 days = 102 of interest
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(352u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+
+            // every NFT of the bundle is returned
+            for item in &collateral {
+                let nft_contract = collection_contract(&app, &cw_template_contract, item.collection_id);
+                assert_eq!(nft_owner(&app, &nft_contract, &item.token_id), BORROWER);
+            }
         }
 
         #[test]
-        fn update_floor_price_fail_cause_not_admin() {
+        fn private_offer() {
             let (mut app, cw_template_contract) = proper_instantiate();
 
+            // one public offer and one negotiated with the borrower
+            for allowed_borrowers in [None, Some(vec![Addr::unchecked(BORROWER)])] {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+
+            let offer_ids = |app: &App, viewer: Option<Addr>| -> Vec<u64> {
+                let resp: OfferListResp = app
+                    .wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferList { start_after: None, limit: None, viewer })
+                    .unwrap();
+                resp.offers.iter().map(|offer| offer.offer_id).collect()
+            };
+            assert_eq!(offer_ids(&app, None), vec![1]);
+            assert_eq!(offer_ids(&app, Some(Addr::unchecked(ANOTHER_USER))), vec![1]);
+            assert_eq!(offer_ids(&app, Some(Addr::unchecked(BORROWER))), vec![1, 2]);
+
             app.execute_contract(
-                Addr::unchecked("user"),
-                cw_template_contract.addr().clone(),
-                &ExecuteMsg::UpdateFloorPrice {collection_id: 1, new_floor_price: 120 },
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 2, token_id: "token123".to_string() },
                 &[],
-            );
+            ).unwrap();
         }
-        
+
         #[test]
-        fn add_new_admin_fail_cause_not_admin() {
+        fn promissory_note_routes_repayment() {
             let (mut app, cw_template_contract) = proper_instantiate();
-            let new_admin = Addr::unchecked("UpdateAdmin");
+            let note_contract = instantiate_position_contract(&mut app, &cw_template_contract, "Notes");
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::SetNoteContract { note_contract: note_contract.clone() },
+                &[],
+            ).unwrap();
 
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
             app.execute_contract(
-                Addr::unchecked("user"),
-                cw_template_contract.addr().clone(),
-                &ExecuteMsg::UpdateAdmin {new_admin: new_admin.clone() },
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
                 &[],
-            );
+            ).unwrap();
+            assert_eq!(nft_owner(&app, &note_contract, "1"), USER);
+
+            // the lender sells the position
+            app.execute_contract(
+                Addr::unchecked(USER),
+                note_contract.clone(),
+                &cw721_base::ExecuteMsg::<NftExtension, Empty>::TransferNft { recipient: ANOTHER_USER.to_string(), token_id: "1".to_string() },
+                &[],
+            ).unwrap();
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            let position = |app: &App| -> PositionStatusResp {
+                app.wrap().query_wasm_smart(cw_template_contract.addr(), &QueryMsg::PositionStatus { offer_id: 1 }).unwrap()
+            };
+            assert_eq!(position(&app), PositionStatusResp { offer_id: 1, live: true, outcome: None });
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(173u128, "SEI".to_string())).unwrap();
+            let res = app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+
+            // principal plus the 123 interest less the 24 protocol fee goes to the note holder
+            let balance = app.wrap().query_balance(ANOTHER_USER, "SEI").unwrap();
+            assert_eq!(balance.amount, Uint128::new(10000 + 50 + 99));
+
+            // the note outlives the loan as a void position its holder may burn
+            assert!(res.has_event(&Event::new("wasm").add_attribute("closed_note", "1")));
+            assert_eq!(position(&app), PositionStatusResp { offer_id: 1, live: false, outcome: Some(OfferStatus::Repaid) });
+            app.execute_contract(
+                Addr::unchecked(ANOTHER_USER),
+                note_contract.clone(),
+                &cw721_base::ExecuteMsg::<NftExtension, Empty>::Burn { token_id: "1".to_string() },
+                &[],
+            ).unwrap();
         }
-        
+
         #[test]
-        fn update_interest_fail_cause_not_admin() {
+        fn borrower_receipt_transfers_repayment_right() {
             let (mut app, cw_template_contract) = proper_instantiate();
+            let receipt_contract = instantiate_position_contract(&mut app, &cw_template_contract, "Receipts");
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::SetReceiptContract { receipt_contract: receipt_contract.clone() },
+                &[],
+            ).unwrap();
 
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
             app.execute_contract(
-                Addr::unchecked("user"),
-                cw_template_contract.addr().clone(),
-                &ExecuteMsg::UpdateInterest {interest: 85 },
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
                 &[],
-            );
+            ).unwrap();
+            assert_eq!(nft_owner(&app, &receipt_contract, "1"), BORROWER);
+
+            // the borrower sells the encumbered NFT by handing over the receipt
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                receipt_contract.clone(),
+                &cw721_base::ExecuteMsg::<NftExtension, Empty>::TransferNft { recipient: ANOTHER_USER.to_string(), token_id: "1".to_string() },
+                &[],
+            ).unwrap();
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: None };
+            let cosmos_msg = cw_template_contract.call(msg.clone(), Coin::new(173u128, "SEI".to_string())).unwrap();
+            let err = app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidBorrow.to_string());
+
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(173u128, "SEI".to_string())).unwrap();
+            let res = app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap();
+            let nft_contract = collection_contract(&app, &cw_template_contract, 1);
+            assert_eq!(nft_owner(&app, &nft_contract, "token123"), ANOTHER_USER);
+
+            // the receipt is void from now on
+            assert!(res.has_event(&Event::new("wasm").add_attribute("closed_receipt", "1")));
+            let position: PositionStatusResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::PositionStatus { offer_id: 1 })
+                .unwrap();
+            assert!(!position.live);
         }
-        
 
         #[test]
-        fn repay_fail_cause_max_time() {
+        fn repay_for_by_third_party() {
             let (mut app, cw_template_contract) = proper_instantiate();
-            // Set amount and collection id to make offer
-            let amount: u128 = 50;
-            let collection_id: u16 = 1;
 
-            // create the offer
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
-            let funds_sent = Coin::new(50u128, "SEI".to_string());
-            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
-            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
-            
-            // borrow nft
-            let token_id = "token123".to_string();
-            
+            for (offer_id, token_id) in [(1, "token123"), (2, "token13")] {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+                app.execute_contract(
+                    Addr::unchecked(BORROWER),
+                    cw_template_contract.addr(),
+                    &ExecuteMsg::Borrow { offer_id, token_id: token_id.to_string() },
+                    &[],
+                ).unwrap();
+            }
+
+            // the borrower lets a repayment of loan 2 deliver the NFT to a vault
             app.execute_contract(
-                Addr::unchecked("borrow"),
-                cw_template_contract.addr().clone(),
-                &ExecuteMsg::Borrow {owner: Addr::unchecked("user"),offer_id: 1, token_id: token_id.clone() },
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::AuthorizeRepayRecipient { offer_id: 2, recipient: Some(Addr::unchecked("vault")) },
                 &[],
             ).unwrap();
 
-            //  update the block_timestamp
             let block = app.block_info();
-            // println!("{:?}", block);
             app.set_block(BlockInfo {
-                height: 12345u64,
-                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 400),
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
                 chain_id: block.chain_id,
             });
-            // repay function
-            let msg = ExecuteMsg::Repay {owner: Addr::unchecked("user"),offer_id: 1 } ;
-            let funds_sent = Coin::new(172u128, "SEI".to_string());
-            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
-            let res = app.execute(Addr::unchecked("borrow"), cosmos_msg); 
 
-            println!("{:?}", res);
+            // a friend repays loan 1 for the borrower and loan 2 into the vault
+            let msg = ExecuteMsg::RepayFor { offer_id: 1, recipient: None, max_payment: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(173u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap();
+            let msg = ExecuteMsg::RepayFor { offer_id: 2, recipient: Some(Addr::unchecked("vault")), max_payment: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(173u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap();
+
+            let nft_contract = collection_contract(&app, &cw_template_contract, 1);
+            assert_eq!(nft_owner(&app, &nft_contract, "token123"), BORROWER);
+            assert_eq!(nft_owner(&app, &nft_contract, "token13"), "vault");
         }
-    }
-
-    mod query {
-        use super::*;
-        use crate::msg::{ExecuteMsg, QueryMsg, OfferResp, OfferListResp, ContractConfig, NFTCollectionResp };
 
         #[test]
-        fn query_by_id() {
+        fn roles_delegate_admin_handlers() {
             let (mut app, cw_template_contract) = proper_instantiate();
-            // Set amount and collection id to make offer
-            let amount: u128 = 50;
-
-            let collection_id: u16 = 1;
 
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
-            let funds_sent = Coin::new(50u128, "SEI".to_string());
-            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
-            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
-            // get the offer by offer_id
-            let resp: OfferResp = app
+            for role in [Role::PriceUpdater, Role::Pauser] {
+                app.execute_contract(
+                    Addr::unchecked(ADMIN),
+                    cw_template_contract.addr(),
+                    &ExecuteMsg::GrantRole { role, address: Addr::unchecked("operator") },
+                    &[],
+                ).unwrap();
+            }
+            let roles: Vec<Role> = app
                 .wrap()
-                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID {offer_id: 1})
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Roles { address: Addr::unchecked("operator") })
                 .unwrap();
+            assert_eq!(roles, vec![Role::PriceUpdater, Role::Pauser]);
 
-            assert_eq!(
+            app.execute_contract(
+                Addr::unchecked("operator"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdateFloorPrice { collection_id: 1, new_floor_price: 120 },
+                &[],
+            ).unwrap();
+
+            // a pauser can pull a lender's offer, the funds go back to the lender
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked("operator"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::CancelOffer { offer_id: 1 },
+                &[],
+            ).unwrap();
+            assert_eq!(app.wrap().query_balance(ANOTHER_USER, DENOM).unwrap().amount.u128(), 10000);
+
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::RevokeRole { role: Role::PriceUpdater, address: Addr::unchecked("operator") },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked("operator"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdateFloorPrice { collection_id: 1, new_floor_price: 150 },
+                &[],
+            ).unwrap_err();
+        }
+
+        #[test]
+        fn fees_accrue_for_fee_collector() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            // reward of 123: 98 for the lender, 24 kept as fees
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 1, max_payment: None }, Coin::new(173u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+
+            let accrued_fees: u128 = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::AccruedFees {})
+                .unwrap();
+            assert_eq!(accrued_fees, 24);
+
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::GrantRole { role: Role::FeeCollector, address: Addr::unchecked("collector") },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked("collector"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::WithdrawFees { amount: Some(20), recipient: Some(Addr::unchecked("treasury")) },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked("collector"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::WithdrawFees { amount: None, recipient: None },
+                &[],
+            ).unwrap();
+
+            assert_eq!(app.wrap().query_balance("treasury", DENOM).unwrap().amount.u128(), 20);
+            assert_eq!(app.wrap().query_balance("collector", DENOM).unwrap().amount.u128(), 4);
+        }
+
+        #[test]
+        fn pause_and_unpause_operations() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::GrantRole { role: Role::Pauser, address: Addr::unchecked("guardian") },
+                &[],
+            ).unwrap();
+            let lend = |app: &mut App, collection_id: u16| {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg)
+            };
+            let pause = |app: &mut App, msg: ExecuteMsg| {
+                app.execute_contract(Addr::unchecked("guardian"), cw_template_contract.addr(), &msg, &[]).unwrap();
+            };
+
+            pause(&mut app, ExecuteMsg::Pause { operations: vec![Operation::Lend], collection_id: None });
+            let err = lend(&mut app, 1).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Paused { operation: "lend".to_string() }.to_string());
+            pause(&mut app, ExecuteMsg::Unpause { operations: vec![Operation::Lend], collection_id: None });
+            lend(&mut app, 1).unwrap();
+            lend(&mut app, 2).unwrap();
+
+            // borrowing stops for collection 2 only, repayments stay open by default and can't be paused
+            pause(&mut app, ExecuteMsg::Pause { operations: vec![Operation::Borrow], collection_id: Some(2) });
+            let err = app.execute_contract(
+                Addr::unchecked("guardian"),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Pause { operations: vec![Operation::Repay], collection_id: None },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::StaysOpenDuringPause { operation: "repay".to_string() }.to_string());
+            let state: PauseStateResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::PauseState {})
+                .unwrap();
+            assert_eq!(state.global, vec![]);
+            assert_eq!(state.collections, vec![CollectionPauseResp { collection_id: 2, operations: vec![Operation::Borrow] }]);
+            assert!(state.settings.repay_open_during_pause);
+
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 2, token_id: "token123".to_string() },
+                &[],
+            ).unwrap_err();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 1, max_payment: None }, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+        }
+
+        #[test]
+        fn add_collection_assigns_id() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let cw721_id = app.store_code(contract_cw721());
+            let nft_contract = instantiate_nft(&mut app, cw721_id, "Collection3");
+
+            let collection = NFTCollectionMsg {
+                collection: "Collection3".to_string(),
+                floor_price: 200,
+                contract: nft_contract.clone(),
+                apy: 3,
+                max_time: 3600 * 24 * 30,
+                risk_tier: 0,
+            };
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::AddNFTCollection { collection },
+                &[],
+            ).unwrap();
+
+            let resp: NFTCollectionResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::CollectionByContract { contract: nft_contract })
+                .unwrap();
+            assert_eq!(resp.collection_id, 3);
+            assert_eq!(resp.collection, "Collection3");
+        }
+
+        #[test]
+        fn collection_lifecycle() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            for (lender, collection_id) in [(USER, 1), (ANOTHER_USER, 2)] {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+                app.execute(Addr::unchecked(lender), cosmos_msg).unwrap();
+            }
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            // new terms and disabling leave the running loan alone
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdateCollection { collection_id: 1, collection: None, contract: None, apy: Some(10), max_time: None, risk_tier: None, disabled: Some(true) },
+                &[],
+            ).unwrap();
+            let collection: NFTCollectionResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::CollectionByID { collection_id: 1 })
+                .unwrap();
+            assert_eq!((collection.apy, collection.disabled), (10, true));
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 1, max_payment: None }, Coin::new(173u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+
+            // delisting refunds the open offers
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::RemoveCollection { collection_id: 2 },
+                &[],
+            ).unwrap();
+            assert_eq!(app.wrap().query_balance(ANOTHER_USER, DENOM).unwrap().amount.u128(), 10000);
+            app.wrap()
+                .query_wasm_smart::<NFTCollectionResp>(cw_template_contract.addr(), &QueryMsg::CollectionByID { collection_id: 2 })
+                .unwrap_err();
+            let repaid: ArchivedOfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::ArchivedOffer { offer_id: 1 })
+                .unwrap();
+            assert_eq!((repaid.outcome, repaid.amount_paid, repaid.fees), (OfferStatus::Repaid, 173, 24));
+            let expired: ArchivedOfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::ArchivedOffer { offer_id: 2 })
+                .unwrap();
+            assert_eq!((expired.outcome, expired.amount_paid), (OfferStatus::Expired, 0));
+            assert_eq!(expired.closed_at, app.block_info().time.seconds());
+        }
+
+        #[test]
+        fn migrate_offers_to_global_ids() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let collection: NFTCollectionResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::CollectionByID { collection_id: 1 })
+                .unwrap();
+
+            // a deployment still holding offers keyed by (owner, u16 id)
+            let legacy_id = app.store_code(contract_legacy());
+            let current_id = app.store_code(contract_template());
+            let contract = instantiate_migratable(&mut app, legacy_id, &cw_template_contract);
+            app.send_tokens(Addr::unchecked(USER), contract.clone(), &[Coin::new(50u128, DENOM)]).unwrap();
+            mint_nft(&mut app, &collection.contract, "escrowed", contract.as_str(), None);
+
+            let res = app.migrate_contract(Addr::unchecked(ADMIN), contract.clone(), &MigrateMsg {}, current_id).unwrap();
+            assert!(res.has_event(
+                &Event::new("wasm")
+                    .add_attribute("from_version", "0.1.0")
+                    .add_attribute("upgrade", "0.2.0")
+            ));
+            let version = cw2::query_contract_info(&app.wrap(), contract.to_string()).unwrap();
+            assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+            let cw_template_contract = CwTemplateContract(contract);
+
+            // listed collections are indexed by contract
+            let listed: NFTCollectionResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::CollectionByContract { contract: collection.contract.clone() })
+                .unwrap();
+            assert_eq!(listed.collection_id, 1);
+            let listed: NFTCollectionListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Collections { start_after: None, limit: None, active_only: None, risk_tier: None, contract: None })
+                .unwrap();
+            assert_eq!(listed.nftcollections[0].stats, LoanStats { open_offers: 1, active_loans: 1, tvl: 50, outstanding_principal: 50, volume: 50, ..LoanStats::default() });
+            let stats: LoanStats = app.wrap().query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Stats {}).unwrap();
+            assert_eq!(stats, listed.nftcollections[0].stats);
+
+            let offer: OfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID { offer_id: 2 })
+                .unwrap();
+            assert_eq!(offer.collateral, vec![Collateral { collection_id: 1, token_id: "escrowed".to_string() }]);
+            // the open offer drops its placeholder borrower
+            let offer: OfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID { offer_id: 1 })
+                .unwrap();
+            assert_eq!((offer.status, offer.borrower), (OfferStatus::Open, None));
+
+            let offers: OfferListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByOwner { owner: Addr::unchecked(USER), start_after: None, limit: None })
+                .unwrap();
+            assert_eq!(offers.offers.iter().map(|offer| offer.offer_id).collect::<Vec<_>>(), vec![1]);
+            let loans: OfferListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByStatus { status: OfferStatus::Active, start_after: None, limit: None, viewer: None })
+                .unwrap();
+            assert_eq!(loans.offers.iter().map(|offer| offer.offer_id).collect::<Vec<_>>(), vec![2]);
+            // the loan took its collection's terms and is indexed by due date
+            let due: LoansDueResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::LoansDueBetween { from: 0, to: u64::MAX, start_after: None, limit: None })
+                .unwrap();
+            assert_eq!(due.loans.len(), 1);
+            assert_eq!(due.loans[0].loan_terms, Some(LoanTerms { apy: 5, max_time: 3600 * 24 * 365 }));
+            // and still holds its collection
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::RemoveCollection { collection_id: 1 },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::CollectionInUse.to_string());
+
+            // new offers continue the sequence
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            let offer: OfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID { offer_id: 3 })
+                .unwrap();
+            assert_eq!(offer.owner, Addr::unchecked(USER));
+
+            // migrated offers are addressed by id alone
+            app.execute_contract(
+                Addr::unchecked(USER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::CancelOffer { offer_id: 1 },
+                &[],
+            ).unwrap();
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 2, max_payment: None }, Coin::new(173u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+            assert_eq!(nft_owner(&app, &collection.contract, "escrowed"), BORROWER);
+        }
+
+        #[test]
+        fn migrate_from_current_version() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let code_id = app.store_code(contract_template());
+            let contract = CwTemplateContract(instantiate_migratable(&mut app, code_id, &cw_template_contract));
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // redeploying the same version runs no upgrade and keeps the state
+            let res = app.migrate_contract(Addr::unchecked(ADMIN), contract.addr(), &MigrateMsg {}, code_id).unwrap();
+            assert!(res.has_event(&Event::new("wasm").add_attribute("from_version", env!("CARGO_PKG_VERSION"))));
+            assert!(!res.events.iter().any(|event| event.attributes.iter().any(|attr| attr.key == "upgrade")));
+
+            let offer: OfferResp = app
+                .wrap()
+                .query_wasm_smart(contract.addr(), &QueryMsg::OfferByID { offer_id: 1 })
+                .unwrap();
+            assert_eq!(offer.owner, Addr::unchecked(USER));
+        }
+
+        #[test]
+        fn repay_refunds_overpayment() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            let balance = |app: &App, address: &str| app.wrap().query_balance(address, DENOM).unwrap().amount.u128();
+            let (borrower_before, lender_before) = (balance(&app, BORROWER), balance(&app, USER));
+
+            // 173 is due, the rest of the payment comes back
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: Some(200) };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(200u128, DENOM)).unwrap();
+            let res = app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+            assert!(res.has_event(&Event::new("wasm").add_attribute("refund", "27")));
+            assert_eq!(balance(&app, BORROWER), borrower_before - 173);
+            assert_eq!(balance(&app, USER), lender_before + 149);
+            // nothing but the protocol fee stays behind
+            assert_eq!(balance(&app, cw_template_contract.addr().as_str()), 24);
+
+            let archived: ArchivedOfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::ArchivedOffer { offer_id: 1 })
+                .unwrap();
+            assert_eq!((archived.amount_paid, archived.fees), (173, 24));
+        }
+
+        #[test]
+        fn offer_status_lifecycle() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let collection: NFTCollectionResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::CollectionByID { collection_id: 2 })
+                .unwrap();
+            for collection_id in [1, 2, 2] {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+            app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::CancelOffer { offer_id: 3 }, &[]).unwrap();
+            for (offer_id, collection_id) in [(1, 1), (2, 2)] {
+                app.execute_contract(
+                    Addr::unchecked(BORROWER),
+                    cw_template_contract.addr(),
+                    &ExecuteMsg::Borrow { offer_id, token_id: "token123".to_string() },
+                    &[],
+                ).unwrap();
+                let offer: OfferResp = app
+                    .wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID { offer_id })
+                    .unwrap();
+                assert_eq!((offer.collection_id, offer.status, offer.borrower), (collection_id, OfferStatus::Active, Some(Addr::unchecked(BORROWER))));
+            }
+
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 1, max_payment: None }, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+
+            // past the due date the lender takes the collateral
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 200),
+                chain_id: block.chain_id,
+            });
+            app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::Liquidate { offer_id: 2 }, &[]).unwrap();
+            assert_eq!(nft_owner(&app, &collection.contract, "token123"), USER);
+
+            // closed offers leave the live offers for the archive, newest first
+            let resp: OfferListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferList { start_after: None, limit: None, viewer: None })
+                .unwrap();
+            assert_eq!(resp.offers, vec![]);
+            // but are still listed by their status
+            for (status, offer_id) in [(OfferStatus::Repaid, 1), (OfferStatus::Liquidated, 2), (OfferStatus::Cancelled, 3)] {
+                let resp: OfferListResp = app
+                    .wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByStatus { status, start_after: None, limit: None, viewer: None })
+                    .unwrap();
+                assert_eq!(resp.offers.iter().map(|offer| (offer.offer_id, offer.status)).collect::<Vec<_>>(), vec![(offer_id, status)]);
+            }
+            let history = |app: &App, msg: &QueryMsg| -> Vec<(u64, OfferStatus)> {
+                let resp: HistoryResp = app.wrap().query_wasm_smart(cw_template_contract.addr(), msg).unwrap();
+                resp.offers.iter().map(|archived| (archived.offer.offer_id, archived.outcome)).collect()
+            };
+            assert_eq!(
+                history(&app, &QueryMsg::HistoryByLender { lender: Addr::unchecked(USER), start_after: None, limit: None }),
+                vec![(3, OfferStatus::Cancelled), (2, OfferStatus::Liquidated), (1, OfferStatus::Repaid)]
+            );
+            assert_eq!(
+                history(&app, &QueryMsg::HistoryByBorrower { borrower: Addr::unchecked(BORROWER), start_after: None, limit: None }),
+                vec![(2, OfferStatus::Liquidated), (1, OfferStatus::Repaid)]
+            );
+            assert_eq!(
+                history(&app, &QueryMsg::HistoryByCollection { collection_id: 2, start_after: Some(3), limit: Some(1) }),
+                vec![(2, OfferStatus::Liquidated)]
+            );
+            let page: HistoryResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::HistoryByLender { lender: Addr::unchecked(USER), start_after: None, limit: Some(2) })
+                .unwrap();
+            assert_eq!(page.next_start_after, Some(2));
+            assert_eq!((page.offers[1].amount_paid, page.offers[1].fees), (0, 0));
+            let listed: NFTCollectionListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Collections { start_after: None, limit: None, active_only: None, risk_tier: None, contract: None })
+                .unwrap();
+            assert_eq!(listed.nftcollections[1].stats, LoanStats { volume: 50, defaults: 1, ..LoanStats::default() });
+        }
+    }
+
+    mod execute_fail {
+        use super::*;
+        use crate::msg::{ExecuteMsg, MigrateMsg, QueryMsg, OfferResp, NFTCollectionResp, TraitFilter, Collateral, Operation, SimulateBorrowResp};
+
+        #[test]
+        fn lend_fail_over_floor_price() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            // Set amount and collection id to make offer
+            let amount: u128 = 200;
+            let collection_id: u16 = 1;
+            // the floor price of collection id  = 1 is 100
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
+            let funds_sent = Coin::new(200u128, "SEI".to_string());
+            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
+            let err = app.execute(Addr::unchecked(USER), cosmos_msg).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::TooMuchLendAmount.to_string());
+        }
+
+        #[test]
+        fn cancel_offer_fail_by_another() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            // Set amount and collection id to make offer
+            let amount: u128 = 50;
+            let collection_id: u16 = 1;
+            let offer_id: u64 = 1;
+
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
+            let funds_sent = Coin::new(50u128, "SEI".to_string());
+            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
+            // create offer
+            let _res: OfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID {offer_id: 1})
+                .unwrap();
+
+            // cancel offer
+            let err = app.execute_contract(
+                Addr::unchecked("another_user"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::CancelOffer { offer_id },
+                &[]
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidOfferOwner.to_string());
+        }
+        
+        #[test]
+        fn borrow_fail_about_accpeted_offer() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            // Set amount and collection id to make offer
+            let amount: u128 = 50;
+            let collection_id: u16 = 1;
+
+            // create the offer
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
+            let funds_sent = Coin::new(50u128, "SEI".to_string());
+            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
+            
+            // borrow nft
+            let token_id = "token123".to_string();
+
+            app.execute_contract(
+                Addr::unchecked("borrow"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::Borrow {offer_id: 1, token_id: token_id.clone() },
+                &[],
+            ).unwrap();
+            // fail because this was already accepted
+            let err = app.execute_contract(
+                Addr::unchecked("another_user"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::Borrow {offer_id: 1, token_id: token_id.clone() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::OfferAlreadyAccepted.to_string());
+        }
+
+        #[test]
+        fn update_floor_price_fail_cause_not_admin() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let err = app.execute_contract(
+                Addr::unchecked("user"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::UpdateFloorPrice {collection_id: 1, new_floor_price: 120 },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+        }
+        
+        #[test]
+        fn add_new_admin_fail_cause_not_admin() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let new_admin = Addr::unchecked("new_admin");
+
+            let err = app.execute_contract(
+                Addr::unchecked("user"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::ProposeAdmin {new_admin: new_admin.clone() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+
+            // only the proposed admin can accept
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::ProposeAdmin {new_admin: new_admin.clone() },
+                &[],
+            ).unwrap();
+            let err = app.execute_contract(
+                Addr::unchecked("user"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::AcceptAdmin {},
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+        }
+        
+        #[test]
+        fn update_interest_fail_cause_not_admin() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let err = app.execute_contract(
+                Addr::unchecked("user"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::UpdateInterest {interest: 85 },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+        }
+
+        #[test]
+        fn interest_fail_above_100() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            // the fee split would overflow on every repayment
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::UpdateInterest { interest: 101 },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidInterest.to_string());
+
+            let code_id = app.store_code(contract_template());
+            let msg = InstantiateMsg { nft_collections: vec![], admin: Addr::unchecked(ADMIN), interest: 101 };
+            let err = app.instantiate_contract(code_id, Addr::unchecked(ADMIN), &msg, &[], "Lend", None).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidInterest.to_string());
+        }
+        
+
+        #[test]
+        fn repay_fail_cause_max_time() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            // Set amount and collection id to make offer
+            let amount: u128 = 50;
+            let collection_id: u16 = 1;
+
+            // create the offer
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
+            let funds_sent = Coin::new(50u128, "SEI".to_string());
+            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
+            
+            // borrow nft
+            let token_id = "token123".to_string();
+            
+            app.execute_contract(
+                Addr::unchecked("borrow"),
+                cw_template_contract.addr().clone(),
+                &ExecuteMsg::Borrow {offer_id: 1, token_id: token_id.clone() },
+                &[],
+            ).unwrap();
+
+            //  update the block_timestamp
+            let block = app.block_info();
+            // println!("{:?}", block);
+            app.set_block(BlockInfo {
+                height: 12345u64,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 400),
+                chain_id: block.chain_id,
+            });
+            // repay function
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: None } ;
+            let funds_sent = Coin::new(172u128, "SEI".to_string());
+            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
+            let res = app.execute(Addr::unchecked("borrow"), cosmos_msg).unwrap();
+
+            // nothing is owed on default, the payment is refunded
+            assert!(res.has_event(&Event::new("transfer").add_attribute("recipient", "borrow").add_attribute("amount", "172SEI")));
+            // the NFT went to the lender instead of the borrower
+            let nft_contract = collection_contract(&app, &cw_template_contract, collection_id);
+            assert_eq!(nft_owner(&app, &nft_contract, &token_id), USER);
+        }
+
+        #[test]
+        fn borrow_fail_over_appraised_value() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // token123 is only worth 40% of the floor price
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::SetTokenAppraisal { collection_id: 1, token_id: "token123".to_string(), multiplier: Some(40) },
+                &[],
+            ).unwrap();
+
+            let err = app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::ExceedsAppraisedValue.to_string());
+        }
+
+        #[test]
+        fn set_appraisal_fail_cause_not_appraiser() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let err = app.execute_contract(
+                Addr::unchecked(USER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::SetTraitAppraisal { collection_id: 1, trait_type: "Background".to_string(), value: "Gold".to_string(), multiplier: Some(300) },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+        }
+
+        #[test]
+        fn borrow_fail_trait_mismatch() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let trait_filter = vec![TraitFilter { trait_type: "Background".to_string(), value: "Gold".to_string() }];
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: Some(trait_filter), bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // token123 has no traits at all
+            let err = app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::TraitMismatch.to_string());
+        }
+
+        #[test]
+        fn borrow_fail_token_mismatch() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: Some("token13".to_string()), trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            let err = app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::TokenMismatch.to_string());
+        }
+
+        #[test]
+        fn borrow_fail_token_from_another_collection() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            // reserved for token13 of collection 1, the borrower also holds token13 of collection 2
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: Some("token13".to_string()), trait_filter: None, bundle: true, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            let err = app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::BorrowBundle { offer_id: 1, collateral: vec![Collateral { collection_id: 2, token_id: "token13".to_string() }] },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::TokenMismatch.to_string());
+        }
+
+        #[test]
+        fn borrow_bundle_fail_not_bundle_offer() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // NFTs of another collection, or of the offer collection, can't be bundled on a plain offer
+            for collateral in [
+                vec![Collateral { collection_id: 2, token_id: "token123".to_string() }],
+                vec![Collateral { collection_id: 1, token_id: "token123".to_string() }],
+            ] {
+                let err = app.execute_contract(
+                    Addr::unchecked(BORROWER),
+                    cw_template_contract.addr(),
+                    &ExecuteMsg::BorrowBundle { offer_id: 1, collateral },
+                    &[],
+                ).unwrap_err();
+                assert_eq!(err.root_cause().to_string(), ContractError::NotBundleOffer.to_string());
+            }
+
+            // the dry run applies the same check
+            let resp: SimulateBorrowResp = app
+                .wrap()
+                .query_wasm_smart(
+                    cw_template_contract.addr(),
+                    &QueryMsg::SimulateBorrow {
+                        offer_id: 1,
+                        borrower: Addr::unchecked(BORROWER),
+                        collateral: vec![Collateral { collection_id: 2, token_id: "token123".to_string() }],
+                    },
+                )
+                .unwrap();
+            assert_eq!(resp.error, Some(ContractError::NotBundleOffer.to_string()));
+        }
+
+        #[test]
+        fn borrow_fail_not_nft_owner() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // token123 belongs to the borrower, not to another_user
+            let err = app.execute_contract(
+                Addr::unchecked(ANOTHER_USER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidNftOwner.to_string());
+        }
+
+        #[test]
+        fn borrow_bundle_fail_over_appraised_value() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 250, collection_id: 1, token_id: None, trait_filter: None, bundle: true, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(250u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // two floor-priced NFTs are only worth 200
+            let collateral = vec![
+                Collateral { collection_id: 1, token_id: "token123".to_string() },
+                Collateral { collection_id: 1, token_id: "token13".to_string() },
+            ];
+            let err = app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::BorrowBundle { offer_id: 1, collateral },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::ExceedsAppraisedValue.to_string());
+
+            // and the same NFT can't be counted twice
+            let collateral = vec![
+                Collateral { collection_id: 1, token_id: "token123".to_string() },
+                Collateral { collection_id: 1, token_id: "token123".to_string() },
+                Collateral { collection_id: 1, token_id: "token13".to_string() },
+            ];
+            let err = app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::BorrowBundle { offer_id: 1, collateral },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidBundle.to_string());
+        }
+
+        #[test]
+        fn borrow_fail_private_offer() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: Some(vec![Addr::unchecked(BORROWER)]) };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            let err = app.execute_contract(
+                Addr::unchecked(ANOTHER_USER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidBorrow.to_string());
+        }
+
+        #[test]
+        fn set_note_contract_fail_cause_not_minter() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            // the collection contracts are minted by the admin
+            let nft_contract = collection_contract(&app, &cw_template_contract, 1);
+
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::SetNoteContract { note_contract: nft_contract },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidMinterContract.to_string());
+        }
+
+        #[test]
+        fn repay_for_fail_unauthorized_recipient() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            // a payer can't redirect the collateral to themselves
+            let msg = ExecuteMsg::RepayFor { offer_id: 1, recipient: Some(Addr::unchecked(ANOTHER_USER)), max_payment: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, "SEI".to_string())).unwrap();
+            let err = app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::UnauthorizedRecipient.to_string());
+        }
+
+        #[test]
+        fn withdraw_fees_fail() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let err = app.execute_contract(
+                Addr::unchecked(USER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::WithdrawFees { amount: None, recipient: None },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+
+            // nothing accrued yet
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::WithdrawFees { amount: None, recipient: None },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidFeeAmount.to_string());
+        }
+
+        #[test]
+        fn pause_fail() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+
+            let err = app.execute_contract(
+                Addr::unchecked(USER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Pause { operations: vec![Operation::Lend], collection_id: None },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            // once the owner closes the exits a pause blocks cancellations too
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdatePauseSettings { repay_open_during_pause: false, cancel_open_during_pause: false },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Pause { operations: vec![Operation::Cancel], collection_id: Some(1) },
+                &[],
+            ).unwrap();
+            let err = app.execute_contract(
+                Addr::unchecked(USER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::CancelOffer { offer_id: 1 },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Paused { operation: "cancel".to_string() }.to_string());
+
+            // and cancellations only reopen once unpaused
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdatePauseSettings { repay_open_during_pause: false, cancel_open_during_pause: true },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Paused { operation: "cancel".to_string() }.to_string());
+        }
+
+        #[test]
+        fn repay_fail_below_due_or_over_max_payment() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(172u128, DENOM)).unwrap();
+            let err = app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InsufficientPayment { due: 173 }.to_string());
+
+            let msg = ExecuteMsg::Repay { offer_id: 1, max_payment: Some(170) };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(200u128, DENOM)).unwrap();
+            let err = app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::MaxPaymentExceeded { due: 173, max_payment: 170 }.to_string());
+        }
+
+        #[test]
+        fn offer_status_lifecycle_fail() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            for _ in 0..2 {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id: 2, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+            app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::CancelOffer { offer_id: 2 }, &[]).unwrap();
+
+            // closed offers are archived and can't be acted on again
+            let err = app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::CancelOffer { offer_id: 2 }, &[]).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::OfferNotFound.to_string());
+            let err = app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 2, token_id: "token123".to_string() },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::OfferNotFound.to_string());
+
+            // open offers can't be liquidated
+            let err = app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::Liquidate { offer_id: 1 }, &[]).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::OfferNotAccepted.to_string());
+
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+            let err = app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::Liquidate { offer_id: 1 }, &[]).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::LoanNotExpired.to_string());
+
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(block.time.seconds() + 200),
+                chain_id: block.chain_id,
+            });
+            // only the lender may liquidate
+            let err = app.execute_contract(Addr::unchecked(BORROWER), cw_template_contract.addr(), &ExecuteMsg::Liquidate { offer_id: 1 }, &[]).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::Unauthorized.to_string());
+
+            app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::Liquidate { offer_id: 1 }, &[]).unwrap();
+            let err = app.execute_contract(Addr::unchecked(BORROWER), cw_template_contract.addr(), &ExecuteMsg::Repay { offer_id: 1, max_payment: None }, &[Coin::new(50u128, DENOM)]).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::OfferNotFound.to_string());
+        }
+
+        #[test]
+        fn collection_lifecycle_fail() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let collection: NFTCollectionResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::CollectionByID { collection_id: 1 })
+                .unwrap();
+
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::AddNFTCollection { collection: listing(collection.clone()) },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::CollectionAlreadyExists.to_string());
+
+            // the lending contract itself is no cw721
+            let not_nft = NFTCollectionMsg { contract: cw_template_contract.addr(), ..listing(collection) };
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::AddNFTCollection { collection: not_nft },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::InvalidNftContract.to_string());
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            let err = app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::RemoveCollection { collection_id: 1 },
+                &[],
+            ).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::CollectionInUse.to_string());
+
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdateCollection { collection_id: 1, collection: None, contract: None, apy: None, max_time: None, risk_tier: None, disabled: Some(true) },
+                &[],
+            ).unwrap();
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            let err = app.execute(Addr::unchecked(USER), cosmos_msg).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::CollectionDisabled.to_string());
+        }
+
+        #[test]
+        fn remove_collection_fail_bundle_collateral() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: true, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::BorrowBundle { offer_id: 1, collateral: vec![Collateral { collection_id: 2, token_id: "token123".to_string() }] },
+                &[],
+            ).unwrap();
+
+            // the loan on collection 1 escrows an NFT of collection 2
+            let remove = |app: &mut App| {
+                app.execute_contract(
+                    Addr::unchecked(ADMIN),
+                    cw_template_contract.addr(),
+                    &ExecuteMsg::RemoveCollection { collection_id: 2 },
+                    &[],
+                )
+            };
+            let err = remove(&mut app).unwrap_err();
+            assert_eq!(err.root_cause().to_string(), ContractError::CollectionInUse.to_string());
+
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 1, max_payment: None }, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+            remove(&mut app).unwrap();
+        }
+
+        #[test]
+        fn migrate_fail_downgrade_or_wrong_contract() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let current_id = app.store_code(contract_template());
+
+            let newer_id = app.store_code(contract_stamped(true));
+            let contract = instantiate_migratable(&mut app, newer_id, &cw_template_contract);
+            let err = app.migrate_contract(Addr::unchecked(ADMIN), contract, &MigrateMsg {}, current_id).unwrap_err();
+            assert_eq!(
+                err.root_cause().to_string(),
+                ContractError::CannotDowngrade { from: "99.0.0".to_string(), to: env!("CARGO_PKG_VERSION").to_string() }.to_string()
+            );
+
+            let foreign_id = app.store_code(contract_stamped(false));
+            let contract = instantiate_migratable(&mut app, foreign_id, &cw_template_contract);
+            let err = app.migrate_contract(Addr::unchecked(ADMIN), contract, &MigrateMsg {}, current_id).unwrap_err();
+            assert_eq!(
+                err.root_cause().to_string(),
+                ContractError::WrongContract { contract: "crates.io:other-lend".to_string() }.to_string()
+            );
+        }
+    }
+
+    mod query {
+        use super::*;
+        use crate::msg::{ExecuteMsg, QueryMsg, OfferResp, OfferListResp, OfferStatus, OffersByPriceResp, PriceCursor, OrderBookDepthResp, DepthLevel, RepayQuoteResp, SimulateBorrowResp, SimulateLendResp, Collateral, LoanTerms, NFTCollectionListResp, CollectionSummaryResp, LoanStats, UserStatsResp, DueCursor, LoansDueResp, ConfigResp, PauseStateResp, PauseSettings, Operation};
+        use cosmwasm_schema::QueryResponses;
+
+        #[test]
+        fn query_by_id() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            // Set amount and collection id to make offer
+            let amount: u128 = 50;
+
+            let collection_id: u16 = 1;
+
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
+            let funds_sent = Coin::new(50u128, "SEI".to_string());
+            let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
+            // get the offer by offer_id
+            let resp: OfferResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferByID {offer_id: 1})
+                .unwrap();
+
+            assert_eq!(
                 resp,
                 OfferResp {
                     offer_id: 1,
@@ -527,8 +2168,16 @@ mod tests {
                     start_time: resp.start_time,
                     collection_id: 1,
                     token_id: "".to_string(),
-                    accepted: false,
-                    borrower: Addr::unchecked("none")
+                    status: OfferStatus::Open,
+                    borrower: None,
+                    trait_filter: None,
+                    bundle: false,
+                    collateral: vec![],
+                    allowed_borrowers: None,
+                    note_contract: None,
+                    receipt_contract: None,
+                    repay_recipient: None,
+                    loan_terms: None,
                 }
             );
         }
@@ -540,7 +2189,7 @@ mod tests {
             let amount: u128 = 50;
             let collection_id: u16 = 1;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(50u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -549,7 +2198,7 @@ mod tests {
             let amount: u128 = 90;
             let collection_id: u16 = 1;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(90u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -558,7 +2207,7 @@ mod tests {
             let amount: u128 = 100;
             let collection_id: u16 = 2;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(100u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -567,18 +2216,29 @@ mod tests {
             let amount: u128 = 120;
             let collection_id: u16 = 2;
             // create offer 4
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(120u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
           
-            let page_size: u16 = 2;
-            let page_num: u16 = 1;
-            let resp: Vec<OfferResp> = app
+            let page: OfferListResp = app
                 .wrap()
-                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferList {page_size: page_size, page_num: page_num})
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferList { start_after: None, limit: Some(2), viewer: None })
                 .unwrap();
-            println!("{:?}", resp);
+            assert_eq!(page.next_start_after, Some(2));
+            let resp = page.offers;
+
+            // the cursor continues after the last offer of the page
+            let next: OfferListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferList { start_after: Some(2), limit: Some(2), viewer: None })
+                .unwrap();
+            assert_eq!(next.offers.iter().map(|offer| offer.offer_id).collect::<Vec<_>>(), vec![3, 4]);
+            let last: OfferListResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OfferList { start_after: Some(4), limit: Some(2), viewer: None })
+                .unwrap();
+            assert_eq!(last, OfferListResp { offers: vec![], next_start_after: None });
 
             assert_eq!(
                 resp,
@@ -590,8 +2250,16 @@ mod tests {
                         start_time: resp[0].start_time,
                         collection_id: 1,
                         token_id: "".to_string(),
-                        accepted: false,
-                        borrower: Addr::unchecked("none")
+                        status: OfferStatus::Open,
+                        borrower: None,
+                        trait_filter: None,
+                        bundle: false,
+                        collateral: vec![],
+                        allowed_borrowers: None,
+                        note_contract: None,
+                        receipt_contract: None,
+                        repay_recipient: None,
+                        loan_terms: None,
                     },
                     OfferResp {
                         offer_id: 2,
@@ -600,8 +2268,16 @@ mod tests {
                         start_time: resp[1].start_time,
                         collection_id: 1,
                         token_id: "".to_string(),
-                        accepted: false,
-                        borrower: Addr::unchecked("none")
+                        status: OfferStatus::Open,
+                        borrower: None,
+                        trait_filter: None,
+                        bundle: false,
+                        collateral: vec![],
+                        allowed_borrowers: None,
+                        note_contract: None,
+                        receipt_contract: None,
+                        repay_recipient: None,
+                        loan_terms: None,
                     }
                 ]
             )
@@ -614,7 +2290,7 @@ mod tests {
             let amount: u128 = 50;
             let collection_id: u16 = 1;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(50u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -623,7 +2299,7 @@ mod tests {
             let amount: u128 = 90;
             let collection_id: u16 = 1;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(90u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -632,7 +2308,7 @@ mod tests {
             let amount: u128 = 100;
             let collection_id: u16 = 2;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(100u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -641,18 +2317,17 @@ mod tests {
             let amount: u128 = 120;
             let collection_id: u16 = 2;
             // create offer 4
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(120u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap(); 
             
-            let page_size: u16 = 2;
-            let page_num: u16 = 1;
-
-            let resp: Vec<OfferResp> = app
+            let page: OfferListResp = app
                 .wrap()
-                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByOwner {owner: Addr::unchecked(ANOTHER_USER), page_size: page_size, page_num: page_num})
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByOwner { owner: Addr::unchecked(ANOTHER_USER), start_after: None, limit: Some(2) })
                 .unwrap();
+            assert_eq!(page.next_start_after, None);
+            let resp = page.offers;
 
             assert_eq!(
                 resp,
@@ -664,8 +2339,16 @@ mod tests {
                         start_time: resp[0].start_time,
                         collection_id: 2,
                         token_id: "".to_string(),
-                        accepted: false,
-                        borrower: Addr::unchecked("none")
+                        status: OfferStatus::Open,
+                        borrower: None,
+                        trait_filter: None,
+                        bundle: false,
+                        collateral: vec![],
+                        allowed_borrowers: None,
+                        note_contract: None,
+                        receipt_contract: None,
+                        repay_recipient: None,
+                        loan_terms: None,
                     }
                 ]
             )
@@ -678,7 +2361,7 @@ mod tests {
             let amount: u128 = 50;
             let collection_id: u16 = 1;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(50u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -687,7 +2370,7 @@ mod tests {
             let amount: u128 = 90;
             let collection_id: u16 = 1;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(90u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
@@ -696,7 +2379,7 @@ mod tests {
             let amount: u128 = 100;
             let collection_id: u16 = 2;
             // create offer 1
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(100u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap(); 
@@ -705,7 +2388,7 @@ mod tests {
             let amount: u128 = 120;
             let collection_id: u16 = 2;
             // create offer 4
-            let msg = ExecuteMsg::Lend {amount: amount, collection_id: collection_id } ;
+            let msg = ExecuteMsg::Lend {amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None } ;
             let funds_sent = Coin::new(120u128, "SEI".to_string());
             let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
             app.execute(Addr::unchecked(ANOTHER_USER), cosmos_msg).unwrap(); 
@@ -714,7 +2397,7 @@ mod tests {
             app.execute_contract(
                 Addr::unchecked("borrow"),
                 cw_template_contract.addr().clone(),
-                &ExecuteMsg::Borrow {owner: Addr::unchecked("user"),offer_id: 1, token_id: token_id.clone() },
+                &ExecuteMsg::Borrow {offer_id: 1, token_id: token_id.clone() },
                 &[],
             ).unwrap();
 
@@ -722,19 +2405,17 @@ mod tests {
             app.execute_contract(
                 Addr::unchecked("borrow"),
                 cw_template_contract.addr().clone(),
-                &ExecuteMsg::Borrow {owner: Addr::unchecked("another_user"),offer_id: 4, token_id: token_id.clone() },
+                &ExecuteMsg::Borrow {offer_id: 4, token_id: token_id.clone() },
                 &[],
             ).unwrap();
 
-            let page_size = 4;
-            let page_num = 1;
-            
-            let resp: Vec<OfferResp> = app
+            // open offers in between don't shorten the page
+            let page: OfferListResp = app
                 .wrap()
-                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersAcceptByBorrow {borrower: Addr::unchecked("borrow"),page_size: page_size, page_num: page_num})
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersAcceptByBorrow { borrower: Addr::unchecked("borrow"), start_after: None, limit: Some(2) })
                 .unwrap();
-
-            println!("data-> {:?}", resp);
+            assert_eq!(page.next_start_after, Some(4));
+            let resp = page.offers;
 
             assert_eq!(
                 resp,
@@ -746,8 +2427,16 @@ mod tests {
                         start_time: resp[0].start_time,
                         collection_id: 1,
                         token_id: "token123".to_string(),
-                        accepted: true,
-                        borrower: Addr::unchecked("borrow")
+                        status: OfferStatus::Active,
+                        borrower: Some(Addr::unchecked("borrow")),
+                        trait_filter: None,
+                        bundle: false,
+                        collateral: vec![Collateral { collection_id: 1, token_id: "token123".to_string() }],
+                        allowed_borrowers: None,
+                        note_contract: None,
+                        receipt_contract: None,
+                        repay_recipient: None,
+                        loan_terms: Some(LoanTerms { apy: 5, max_time: 3600 * 24 * 365 }),
                     },
                     OfferResp {
                         offer_id: 4,
@@ -756,8 +2445,16 @@ mod tests {
                         start_time: resp[1].start_time,
                         collection_id: 2,
                         token_id: "token13".to_string(),
-                        accepted: true,
-                        borrower: Addr::unchecked("borrow")
+                        status: OfferStatus::Active,
+                        borrower: Some(Addr::unchecked("borrow")),
+                        trait_filter: None,
+                        bundle: false,
+                        collateral: vec![Collateral { collection_id: 2, token_id: "token13".to_string() }],
+                        allowed_borrowers: None,
+                        note_contract: None,
+                        receipt_contract: None,
+                        repay_recipient: None,
+                        loan_terms: Some(LoanTerms { apy: 7, max_time: 130 }),
                     }
                 ]
             )
@@ -771,18 +2468,429 @@ mod tests {
             for i in 0..1000 {
                 let amount: u128 = i % 100 + 1 ; // Varying amount
                 let collection_id: u16 = ((i % 2) + 1).try_into().unwrap();   // Varying collection_id
-                let msg = ExecuteMsg::Lend { amount, collection_id };
+                let msg = ExecuteMsg::Lend { amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
                 let funds_sent = Coin::new(amount, "SEI".to_string());
                 let cosmos_msg = cw_template_contract.call(msg, funds_sent).unwrap();
                 app.execute(Addr::unchecked(USER), cosmos_msg).unwrap(); 
             }
 
-            let resp: Vec<OfferResp> = app
+            // highest first, walking every page above 85
+            let mut amounts = vec![];
+            let mut start_after: Option<PriceCursor> = None;
+            loop {
+                let page: OffersByPriceResp = app
+                    .wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByPrice { min_amount: 85, sort: true, start_after, limit: Some(30), viewer: None })
+                    .unwrap();
+                amounts.extend(page.offers.iter().map(|offer| offer.amount));
+                match page.next_start_after {
+                    Some(cursor) => start_after = Some(cursor),
+                    None => break,
+                }
+            }
+            assert_eq!(amounts.len(), 150);
+            assert_eq!(amounts[0], 100);
+            assert!(amounts.windows(2).all(|pair| pair[0] >= pair[1]));
+            assert!(amounts.iter().all(|amount| *amount > 85));
+
+            let page: OffersByPriceResp = app
                 .wrap()
-                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByPrice {page:10, page_size: 10,limit: 85, sort: true})
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OffersByPrice { min_amount: 85, sort: false, start_after: None, limit: Some(3), viewer: None })
                 .unwrap();
-            println!("{:?}", resp);
+            assert_eq!(page.offers.iter().map(|offer| (offer.offer_id, offer.amount)).collect::<Vec<_>>(), vec![(86, 86), (186, 86), (286, 86)]);
+            assert_eq!(page.next_start_after, Some(PriceCursor { amount: 86, offer_id: 286 }));
+        }
+
+        #[test]
+        fn offers_by_collection_and_status() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            for collection_id in [1, 2, 1] {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            let offer_ids = |app: &App, msg: &QueryMsg| -> Vec<u64> {
+                let resp: OfferListResp = app.wrap().query_wasm_smart(cw_template_contract.addr(), msg).unwrap();
+                resp.offers.iter().map(|offer| offer.offer_id).collect()
+            };
+            assert_eq!(offer_ids(&app, &QueryMsg::OffersByCollection { collection_id: 1, start_after: None, limit: None, viewer: None }), vec![1, 3]);
+            assert_eq!(offer_ids(&app, &QueryMsg::OffersByCollection { collection_id: 1, start_after: Some(1), limit: None, viewer: None }), vec![3]);
+            assert_eq!(offer_ids(&app, &QueryMsg::OffersByStatus { status: OfferStatus::Open, start_after: None, limit: None, viewer: None }), vec![2, 3]);
+            assert_eq!(offer_ids(&app, &QueryMsg::OffersByStatus { status: OfferStatus::Active, start_after: None, limit: None, viewer: None }), vec![1]);
+
+            // repaying drops the loan from every index
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Repay { offer_id: 1, max_payment: None },
+                &[Coin::new(50u128, DENOM)],
+            ).unwrap();
+            assert_eq!(offer_ids(&app, &QueryMsg::OffersByStatus { status: OfferStatus::Active, start_after: None, limit: None, viewer: None }), Vec::<u64>::new());
+            assert_eq!(offer_ids(&app, &QueryMsg::OffersAcceptByBorrow { borrower: Addr::unchecked(BORROWER), start_after: None, limit: None }), Vec::<u64>::new());
+        }
+
+        #[test]
+        fn order_book_and_depth() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            for (collection_id, amount) in [(1, 50u128), (1, 80), (1, 50), (1, 30), (2, 100), (1, 80)] {
+                let msg = ExecuteMsg::Lend { amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(amount, DENOM)).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+            let msg = ExecuteMsg::Lend { amount: 90, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: Some(vec![Addr::unchecked(BORROWER)]) };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(90u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+
+            let book = |app: &App, sort: bool, start_after: Option<PriceCursor>, viewer: Option<Addr>| -> OffersByPriceResp {
+                app.wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OrderBook { collection_id: 1, sort, start_after, limit: Some(2), viewer })
+                    .unwrap()
+            };
+            let offer_ids = |page: &OffersByPriceResp| -> Vec<u64> { page.offers.iter().map(|offer| offer.offer_id).collect() };
+
+            // best first, the loan and the other collection are left out
+            let page = book(&app, true, None, None);
+            assert_eq!(offer_ids(&page), vec![6, 2]);
+            assert_eq!(page.next_start_after, Some(PriceCursor { amount: 80, offer_id: 2 }));
+            let page = book(&app, true, page.next_start_after, None);
+            assert_eq!(offer_ids(&page), vec![3, 4]);
+            let page = book(&app, true, page.next_start_after, None);
+            assert_eq!(page, OffersByPriceResp { offers: vec![], next_start_after: None });
+
+            assert_eq!(offer_ids(&book(&app, false, None, None)), vec![4, 3]);
+            assert_eq!(offer_ids(&book(&app, true, None, Some(Addr::unchecked(BORROWER)))), vec![7, 6]);
+
+            let depth: OrderBookDepthResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OrderBookDepth { collection_id: 1, levels: None })
+                .unwrap();
+            assert_eq!(depth.levels, vec![
+                DepthLevel { amount: 80, offers: 2, total: 160 },
+                DepthLevel { amount: 50, offers: 1, total: 50 },
+                DepthLevel { amount: 30, offers: 1, total: 30 },
+            ]);
+            let depth: OrderBookDepthResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::OrderBookDepth { collection_id: 1, levels: Some(1) })
+                .unwrap();
+            assert_eq!(depth.levels, vec![DepthLevel { amount: 80, offers: 2, total: 160 }]);
+        }
+
+        #[test]
+        fn repay_quote_and_simulations() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let simulate_lend = |app: &App, amount: u128, collection_id: u16| -> SimulateLendResp {
+                app.wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::SimulateLend { amount, collection_id, token_id: None, bundle: false })
+                    .unwrap()
+            };
+            assert_eq!(simulate_lend(&app, 50, 1), SimulateLendResp { error: None, offer_id: 1, max_amount: Some(100) });
+            assert_eq!(simulate_lend(&app, 200, 1).error, Some(ContractError::TooMuchLendAmount.to_string()));
+            assert_eq!(simulate_lend(&app, 50, 9).error, Some(ContractError::CollectionNotFound.to_string()));
+            // bundle offers are uncapped until borrowed against, plain offers may leave the flag out
+            let bundle: SimulateLendResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::SimulateLend { amount: 500, collection_id: 1, token_id: None, bundle: true })
+                .unwrap();
+            assert_eq!(bundle, SimulateLendResp { error: None, offer_id: 1, max_amount: None });
+            let plain: SimulateLendResp = app
+                .wrap()
+                .query_wasm_smart(cw_template_contract.addr(), &serde_json::json!({ "simulate_lend": { "amount": "50", "collection_id": 1 } }))
+                .unwrap();
+            assert_eq!(plain.max_amount, Some(100));
+
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+
+            let collateral = vec![Collateral { collection_id: 1, token_id: "token123".to_string() }];
+            let simulate_borrow = |app: &App, borrower: &str| -> SimulateBorrowResp {
+                app.wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::SimulateBorrow { offer_id: 1, borrower: Addr::unchecked(borrower), collateral: collateral.clone() })
+                    .unwrap()
+            };
+            assert_eq!(simulate_borrow(&app, BORROWER), SimulateBorrowResp {
+                error: None,
+                payout: 50,
+                appraised_value: 100,
+                loan_terms: Some(LoanTerms { apy: 5, max_time: 3600 * 24 * 365 }),
+            });
+            assert_eq!(simulate_borrow(&app, ANOTHER_USER).error, Some(ContractError::InvalidNftOwner.to_string()));
+
+            // only loans have a quote
+            app.wrap()
+                .query_wasm_smart::<RepayQuoteResp>(cw_template_contract.addr(), &QueryMsg::RepayQuote { loan_id: 1, at_time: None })
+                .unwrap_err();
+
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+            assert_eq!(simulate_borrow(&app, BORROWER).error, Some(ContractError::OfferAlreadyAccepted.to_string()));
+
+            let start_time = app.block_info().time.seconds();
+            let quote_at = |app: &App, at_time: Option<u64>| -> RepayQuoteResp {
+                app.wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::RepayQuote { loan_id: 1, at_time })
+                    .unwrap()
+            };
+            let quote = quote_at(&app, Some(start_time + 3600 * 24 * 180));
+            assert_eq!(quote, RepayQuoteResp {
+                loan_id: 1,
+                principal: 50,
+                interest: 123,
+                protocol_fee: 24,
+                late_fee: 0,
+                total_due: 173,
+                due_date: start_time + 3600 * 24 * 365,
+                overdue: false,
+            });
+            assert!(quote_at(&app, Some(start_time + 3600 * 24 * 366)).overdue);
+
+            // the quote for the current block is what repay takes
+            let block = app.block_info();
+            app.set_block(BlockInfo {
+                height: block.height + 1,
+                time: Timestamp::from_seconds(start_time + 3600 * 24 * 180),
+                chain_id: block.chain_id,
+            });
+            let quote = quote_at(&app, None);
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 1, max_payment: None }, Coin::new(quote.total_due, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+        }
+
+        #[test]
+        fn config_and_response_schemas() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let msg = ExecuteMsg::Lend { amount: 50, collection_id: 1, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+            let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            for msg in [
+                ExecuteMsg::ProposeAdmin { new_admin: Addr::unchecked("new_admin") },
+                ExecuteMsg::Pause { operations: vec![Operation::Lend], collection_id: None },
+            ] {
+                app.execute_contract(Addr::unchecked(ADMIN), cw_template_contract.addr(), &msg, &[]).unwrap();
+            }
+
+            let config: ConfigResp = app.wrap().query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Config {}).unwrap();
+            assert_eq!(
+                config,
+                ConfigResp {
+                    admin: Addr::unchecked(ADMIN),
+                    pending_admin: Some(Addr::unchecked("new_admin")),
+                    interest: INTEREST,
+                    accrued_fees: 0,
+                    denom: DENOM.to_string(),
+                    last_offer_id: 1,
+                    last_collection_id: 2,
+                    note_contract: None,
+                    receipt_contract: None,
+                    pause: PauseStateResp { global: vec![Operation::Lend], collections: vec![], settings: PauseSettings::default() },
+                }
+            );
+
+            // every query declares its response type
+            let schemas = QueryMsg::response_schemas().unwrap();
+            assert!(schemas.contains_key("config"));
+            assert!(schemas.contains_key("offer_list"));
+            // and every message is snake case on the wire
+            assert_eq!(cosmwasm_std::to_json_string(&QueryMsg::Config {}).unwrap(), r#"{"config":{}}"#);
+            assert_eq!(cosmwasm_std::to_json_string(&ExecuteMsg::AcceptAdmin {}).unwrap(), r#"{"accept_admin":{}}"#);
+        }
+
+        #[test]
+        fn loans_due_and_overdue() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            let start = app.block_info().time.seconds();
+            for collection_id in [1, 2, 2, 1] {
+                let msg = ExecuteMsg::Lend { amount: 50, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(50u128, DENOM)).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+            for (offer_id, token_id) in [(1, "token123"), (2, "token123"), (3, "token13")] {
+                app.execute_contract(
+                    Addr::unchecked(BORROWER),
+                    cw_template_contract.addr(),
+                    &ExecuteMsg::Borrow { offer_id, token_id: token_id.to_string() },
+                    &[],
+                ).unwrap();
+            }
+
+            let loans = |app: &App, msg: &QueryMsg| -> (Vec<u64>, Option<DueCursor>) {
+                let resp: LoansDueResp = app.wrap().query_wasm_smart(cw_template_contract.addr(), msg).unwrap();
+                (resp.loans.iter().map(|loan| loan.offer_id).collect(), resp.next_start_after)
+            };
+
+            // collection 2 loans are due after 130 seconds, the open offer never is
+            assert_eq!(loans(&app, &QueryMsg::LoansDueBetween { from: 0, to: u64::MAX, start_after: None, limit: None }).0, vec![2, 3, 1]);
+            let (page, next) = loans(&app, &QueryMsg::LoansDueBetween { from: start, to: start + 130, start_after: None, limit: Some(1) });
+            assert_eq!((page, next.clone()), (vec![2], Some(DueCursor { due_date: start + 130, loan_id: 2 })));
+            assert_eq!(loans(&app, &QueryMsg::LoansDueBetween { from: start, to: start + 130, start_after: next, limit: Some(1) }).0, vec![3]);
+            assert_eq!(loans(&app, &QueryMsg::LoansDueBetween { from: start + 131, to: start + 3600 * 24 * 365, start_after: None, limit: None }).0, vec![1]);
+
+            let advance = |app: &mut App, seconds: u64| {
+                let block = app.block_info();
+                app.set_block(BlockInfo {
+                    height: block.height + 1,
+                    time: Timestamp::from_seconds(block.time.seconds() + seconds),
+                    chain_id: block.chain_id,
+                });
+            };
+            // a loan due now can still be repaid
+            advance(&mut app, 130);
+            assert_eq!(loans(&app, &QueryMsg::OverdueLoans { start_after: None, limit: None }), (vec![], None));
+            advance(&mut app, 1);
+            assert_eq!(loans(&app, &QueryMsg::OverdueLoans { start_after: None, limit: None }).0, vec![2, 3]);
+
+            app.execute_contract(Addr::unchecked(USER), cw_template_contract.addr(), &ExecuteMsg::Liquidate { offer_id: 2 }, &[]).unwrap();
+            assert_eq!(loans(&app, &QueryMsg::OverdueLoans { start_after: None, limit: None }).0, vec![3]);
+        }
+
+        #[test]
+        fn protocol_collection_and_user_stats() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            for (lender, collection_id, amount) in [(USER, 1, 50u128), (USER, 1, 80), (ANOTHER_USER, 2, 100), (ANOTHER_USER, 2, 30)] {
+                let msg = ExecuteMsg::Lend { amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(amount, DENOM)).unwrap();
+                app.execute(Addr::unchecked(lender), cosmos_msg).unwrap();
+            }
+            let stats = |app: &App, msg: &QueryMsg| -> LoanStats { app.wrap().query_wasm_smart(cw_template_contract.addr(), msg).unwrap() };
+            let user_stats = |app: &App, address: &str| -> UserStatsResp {
+                app.wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::UserStats { address: Addr::unchecked(address) })
+                    .unwrap()
+            };
+            assert_eq!(stats(&app, &QueryMsg::Stats {}), LoanStats { open_offers: 4, tvl: 260, ..LoanStats::default() });
+
+            let borrow = |app: &mut App, offer_id: u64| {
+                app.execute_contract(
+                    Addr::unchecked(BORROWER),
+                    cw_template_contract.addr(),
+                    &ExecuteMsg::Borrow { offer_id, token_id: "token123".to_string() },
+                    &[],
+                ).unwrap();
+            };
+            let advance = |app: &mut App, seconds: u64| {
+                let block = app.block_info();
+                app.set_block(BlockInfo {
+                    height: block.height + 1,
+                    time: Timestamp::from_seconds(block.time.seconds() + seconds),
+                    chain_id: block.chain_id,
+                });
+            };
+
+            // repaid with 123 interest, 24 of it protocol fees
+            borrow(&mut app, 1);
+            advance(&mut app, 3600 * 24 * 180);
+            let cosmos_msg = cw_template_contract.call(ExecuteMsg::Repay { offer_id: 1, max_payment: None }, Coin::new(173u128, DENOM)).unwrap();
+            app.execute(Addr::unchecked(BORROWER), cosmos_msg).unwrap();
+
+            // liquidated, cancelled and still running
+            borrow(&mut app, 3);
+            advance(&mut app, 200);
+            app.execute_contract(Addr::unchecked(ANOTHER_USER), cw_template_contract.addr(), &ExecuteMsg::Liquidate { offer_id: 3 }, &[]).unwrap();
+            app.execute_contract(Addr::unchecked(ANOTHER_USER), cw_template_contract.addr(), &ExecuteMsg::CancelOffer { offer_id: 4 }, &[]).unwrap();
+            borrow(&mut app, 2);
+
+            assert_eq!(
+                stats(&app, &QueryMsg::Stats {}),
+                LoanStats { active_loans: 1, outstanding_principal: 80, volume: 230, interest_earned: 99, fees_collected: 24, defaults: 1, ..LoanStats::default() }
+            );
+            assert_eq!(
+                stats(&app, &QueryMsg::CollectionStats { collection_id: 1 }),
+                LoanStats { active_loans: 1, outstanding_principal: 80, volume: 130, interest_earned: 99, fees_collected: 24, ..LoanStats::default() }
+            );
+            assert_eq!(stats(&app, &QueryMsg::CollectionStats { collection_id: 2 }), LoanStats { volume: 100, defaults: 1, ..LoanStats::default() });
+
+            let lender = user_stats(&app, USER);
+            assert_eq!(lender.as_lender, stats(&app, &QueryMsg::CollectionStats { collection_id: 1 }));
+            assert_eq!(lender.as_borrower, LoanStats::default());
+            assert_eq!(user_stats(&app, ANOTHER_USER).as_lender, LoanStats { volume: 100, defaults: 1, ..LoanStats::default() });
+            let borrower = user_stats(&app, BORROWER);
+            assert_eq!(borrower.as_borrower, stats(&app, &QueryMsg::Stats {}));
+            assert_eq!(borrower.as_lender, LoanStats::default());
+
+            app.wrap()
+                .query_wasm_smart::<LoanStats>(cw_template_contract.addr(), &QueryMsg::CollectionStats { collection_id: 9 })
+                .unwrap_err();
+        }
+
+        #[test]
+        fn collections_with_stats() {
+            let (mut app, cw_template_contract) = proper_instantiate();
+            for (collection_id, amount) in [(1, 50u128), (1, 80), (2, 100)] {
+                let msg = ExecuteMsg::Lend { amount, collection_id, token_id: None, trait_filter: None, bundle: false, allowed_borrowers: None };
+                let cosmos_msg = cw_template_contract.call(msg, Coin::new(amount, DENOM)).unwrap();
+                app.execute(Addr::unchecked(USER), cosmos_msg).unwrap();
+            }
+            app.execute_contract(
+                Addr::unchecked(BORROWER),
+                cw_template_contract.addr(),
+                &ExecuteMsg::Borrow { offer_id: 1, token_id: "token123".to_string() },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdateCollection { collection_id: 2, collection: None, contract: None, apy: None, max_time: None, risk_tier: Some(2), disabled: None },
+                &[],
+            ).unwrap();
+            app.execute_contract(
+                Addr::unchecked(ADMIN),
+                cw_template_contract.addr(),
+                &ExecuteMsg::UpdateCollection { collection_id: 1, collection: None, contract: None, apy: None, max_time: None, risk_tier: None, disabled: Some(true) },
+                &[],
+            ).unwrap();
+
+            let collections = |start_after: Option<u16>, limit: Option<u32>, active_only: Option<bool>, risk_tier: Option<u8>, contract: Option<Addr>| {
+                let resp: NFTCollectionListResp = app
+                    .wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Collections { start_after, limit, active_only, risk_tier, contract })
+                    .unwrap();
+                resp.nftcollections
+            };
+            let ids = |summaries: Vec<CollectionSummaryResp>| -> Vec<u16> {
+                summaries.iter().map(|summary| summary.collection.collection_id).collect()
+            };
+
+            let all = collections(None, None, None, None, None);
+            assert_eq!(all[0].stats, LoanStats { open_offers: 1, active_loans: 1, tvl: 80, outstanding_principal: 50, volume: 50, ..LoanStats::default() });
+            assert_eq!(all[0].best_offer, Some(80));
+            assert_eq!(all[1].stats, LoanStats { open_offers: 1, tvl: 100, ..LoanStats::default() });
+            assert_eq!(all[1].best_offer, Some(100));
+
+            assert_eq!(ids(collections(Some(1), None, None, None, None)), vec![2]);
+            assert_eq!(ids(collections(None, Some(1), None, None, None)), vec![1]);
+            assert_eq!(ids(collections(None, None, None, Some(2), None)), vec![2]);
+            assert_eq!(ids(collections(None, None, Some(true), None, None)), vec![2]);
+            assert_eq!(ids(collections(None, None, None, None, Some(all[0].collection.contract.clone()))), vec![1]);
+
+            // the cursor points past the last collection of a full page
+            let page = |start_after: Option<u16>, active_only: Option<bool>| -> Option<u16> {
+                let resp: NFTCollectionListResp = app
+                    .wrap()
+                    .query_wasm_smart(cw_template_contract.addr(), &QueryMsg::Collections { start_after, limit: Some(1), active_only, risk_tier: None, contract: None })
+                    .unwrap();
+                resp.next_start_after
+            };
+            assert_eq!(page(None, None), Some(1));
+            assert_eq!(page(None, Some(true)), Some(2));
+            assert_eq!(page(Some(2), None), None);
         }
-    
     }
 }
@@ -1,7 +1,7 @@
 use cosmwasm_std::{
     entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
 };
-use msg::{ ExecuteMsg, InstantiateMsg, QueryMsg };
+use msg::{ ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg };
 
 pub mod contract;
 mod error;
@@ -9,13 +9,14 @@ pub mod msg;
 pub mod state;
 pub mod integration_tests;
 pub mod helpers;
+pub mod upgrades;
 
 pub use crate::error::ContractError;
 
 
 #[entry_point]
 pub fn instantiate(deps: DepsMut, _env: Env, _info: MessageInfo, msg: InstantiateMsg)
-  -> StdResult<Response>
+  -> Result<Response, ContractError>
 {
     contract::instantiate(deps, _env, _info, msg)
 }
@@ -26,6 +27,12 @@ pub fn execute(deps: DepsMut, _env: Env, info: MessageInfo, msg: ExecuteMsg) ->
   contract::execute(deps, _env, info, msg)
 }
 
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError>
+{
+  contract::migrate(deps, _env, msg)
+}
+
 #[entry_point]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg)
   -> StdResult<Binary>
@@ -1,91 +1,508 @@
-// use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{ Addr };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cw_storage_plus::{UniqueIndex/*,MultiIndex*/, IndexedMap, Index, IndexList};
+use cw_storage_plus::{MultiIndex, IndexedMap, Index, IndexList};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cw_serde]
 pub struct InstantiateMsg {
-    pub nft_collections: Vec<NFTCollectionResp>,
+    pub nft_collections: Vec<NFTCollectionMsg>,
     // pub offers: Vec<OfferResp>,
     pub admin: Addr, 
     pub interest: u128,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[cw_serde]
 pub enum ExecuteMsg {
-    Lend { amount: u128, collection_id: u16 },
-    CancelOffer { offer_id: u16 },
-    Borrow { owner: Addr, offer_id: u16, token_id: String },
+    Lend { amount: u128, collection_id: u16, token_id: Option<String>, trait_filter: Option<Vec<TraitFilter>>, #[serde(default)] bundle: bool, allowed_borrowers: Option<Vec<Addr>> },
+    CancelOffer { offer_id: u64 },
+    Borrow { offer_id: u64, token_id: String },
+    BorrowBundle { offer_id: u64, collateral: Vec<Collateral> },
     UpdateFloorPrice { collection_id: u16, new_floor_price: u128 },
-    AddNFTCollection { collection: NFTCollectionResp },
-    UpdateAdmin { new_admin: Addr },
+    AddNFTCollection { collection: NFTCollectionMsg },
+    UpdateCollection { collection_id: u16, collection: Option<String>, contract: Option<Addr>, apy: Option<u16>, max_time: Option<u64>, risk_tier: Option<u8>, disabled: Option<bool> },
+    RemoveCollection { collection_id: u16 },
+    ProposeAdmin { new_admin: Addr },
+    AcceptAdmin {},
+    GrantRole { role: Role, address: Addr },
+    RevokeRole { role: Role, address: Addr },
+    WithdrawFees { amount: Option<u128>, recipient: Option<Addr> },
+    Pause { operations: Vec<Operation>, collection_id: Option<u16> },
+    Unpause { operations: Vec<Operation>, collection_id: Option<u16> },
+    UpdatePauseSettings { repay_open_during_pause: bool, cancel_open_during_pause: bool },
     UpdateInterest { interest: u128 },
-    Repay {owner: Addr, offer_id: u16},
+    // Pays at least the amount due, the excess goes back to the payer.
+    // Fails when more than `max_payment` is due.
+    Repay { offer_id: u64, max_payment: Option<u128> },
+    // The lender claims the collateral of a loan past its due date
+    Liquidate { offer_id: u64 },
+    RepayFor { offer_id: u64, recipient: Option<Addr>, max_payment: Option<u128> },
+    AuthorizeRepayRecipient { offer_id: u64, recipient: Option<Addr> },
+    SetTokenAppraisal { collection_id: u16, token_id: String, multiplier: Option<u16> },
+    SetTraitAppraisal { collection_id: u16, trait_type: String, value: String, multiplier: Option<u16> },
+    SetNoteContract { note_contract: Addr },
+    SetReceiptContract { receipt_contract: Addr },
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cw_serde]
+#[derive(QueryResponses)]
 pub enum QueryMsg {
-    OfferList { page_size: u16, page_num: u16},
-    OfferByID {offer_id: u16},
-    OffersByOwner {owner: Addr, page_size: u16, page_num: u16}, 
-
-    OffersAcceptByBorrow {borrower: Addr ,page_size: u16, page_num: u16}, 
-    OffersByPrice {page:u16, page_size:u16, limit: u128, sort: bool},
+    #[returns(OfferListResp)]
+    OfferList { start_after: Option<u64>, limit: Option<u32>, viewer: Option<Addr> },
+    #[returns(OfferResp)]
+    OfferByID {offer_id: u64},
+    #[returns(OfferListResp)]
+    OffersByOwner { owner: Addr, start_after: Option<u64>, limit: Option<u32> },
+    #[returns(OfferListResp)]
+    OffersAcceptByBorrow { borrower: Addr, start_after: Option<u64>, limit: Option<u32> },
+    #[returns(OfferListResp)]
+    OffersByCollection { collection_id: u16, start_after: Option<u64>, limit: Option<u32>, viewer: Option<Addr> },
+    #[returns(OfferListResp)]
+    OffersByStatus { status: OfferStatus, start_after: Option<u64>, limit: Option<u32>, viewer: Option<Addr> },
+    // what repaying a loan costs at `at_time`, now by default
+    #[returns(RepayQuoteResp)]
+    RepayQuote { loan_id: u64, at_time: Option<u64> },
+    // dry runs reporting the error the message would fail with
+    #[returns(SimulateBorrowResp)]
+    SimulateBorrow { offer_id: u64, borrower: Addr, collateral: Vec<Collateral> },
+    #[returns(SimulateLendResp)]
+    SimulateLend { amount: u128, collection_id: u16, token_id: Option<String>, #[serde(default)] bundle: bool },
+    // closed offers and loans, newest first
+    #[returns(ArchivedOfferResp)]
+    ArchivedOffer { offer_id: u64 },
+    #[returns(HistoryResp)]
+    HistoryByLender { lender: Addr, start_after: Option<u64>, limit: Option<u32> },
+    #[returns(HistoryResp)]
+    HistoryByBorrower { borrower: Addr, start_after: Option<u64>, limit: Option<u32> },
+    #[returns(HistoryResp)]
+    HistoryByCollection { collection_id: u16, start_after: Option<u64>, limit: Option<u32> },
+    // active loans due between `from` and `to` inclusive, soonest first
+    #[returns(LoansDueResp)]
+    LoansDueBetween { from: u64, to: u64, start_after: Option<DueCursor>, limit: Option<u32> },
+    // active loans past their due date, most overdue first
+    #[returns(LoansDueResp)]
+    OverdueLoans { start_after: Option<DueCursor>, limit: Option<u32> },
+    // offers above `min_amount`, highest first when `sort` is set
+    #[returns(OffersByPriceResp)]
+    OffersByPrice { min_amount: u128, sort: bool, start_after: Option<PriceCursor>, limit: Option<u32>, viewer: Option<Addr> },
+    // open offers of a collection, highest first when `sort` is set
+    #[returns(OffersByPriceResp)]
+    OrderBook { collection_id: u16, sort: bool, start_after: Option<PriceCursor>, limit: Option<u32>, viewer: Option<Addr> },
+    // public open offers of a collection aggregated by amount, highest first
+    #[returns(OrderBookDepthResp)]
+    OrderBookDepth { collection_id: u16, levels: Option<u32> },
+    // running totals, globally, for a collection and for an address
+    #[returns(LoanStats)]
+    Stats {},
+    #[returns(LoanStats)]
+    CollectionStats { collection_id: u16 },
+    #[returns(UserStatsResp)]
+    UserStats { address: Addr },
+    #[returns(NFTCollectionResp)]
     CollectionByID { collection_id: u16 },
+    #[returns(NFTCollectionResp)]
+    CollectionByContract { contract: Addr },
+    #[returns(NFTCollectionListResp)]
+    Collections { start_after: Option<u16>, limit: Option<u32>, active_only: Option<bool>, risk_tier: Option<u8>, contract: Option<Addr> },
+    // admin and interest split only, see `Config` for everything else
+    #[returns(ContractConfig)]
     QueryAdmin {},
+    // the whole configuration with id counters, fees and pause state
+    #[returns(ConfigResp)]
+    Config {},
+    #[returns(Option<Addr>)]
+    PendingAdmin {},
+    #[returns(Vec<Role>)]
+    Roles { address: Addr },
+    #[returns(u128)]
+    AccruedFees {},
+    #[returns(PauseStateResp)]
+    PauseState {},
+    #[returns(Option<Addr>)]
+    NoteContract {},
+    // whether the promissory note and borrower receipt of a loan are still backed by it
+    #[returns(PositionStatusResp)]
+    PositionStatus { offer_id: u64 },
+    #[returns(Option<Addr>)]
+    ReceiptContract {},
+    #[returns(AppraisalResp)]
+    AppraisedValue { collection_id: u16, token_id: String },
+    // open offers reserved for a token, highest first
+    #[returns(OffersByPriceResp)]
+    OffersByToken { collection_id: u16, token_id: String, start_after: Option<PriceCursor>, limit: Option<u32>, viewer: Option<Addr> },
 }
 
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
 pub struct NFTCollectionListResp {
-   pub nftcollections: Vec<NFTCollectionResp>,
+   pub nftcollections: Vec<CollectionSummaryResp>,
+   // Pass as `start_after` to fetch the next page, none on the last page
+   pub next_start_after: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct CollectionSummaryResp {
+    pub collection: NFTCollectionResp,
+    pub stats: LoanStats,
+    // Highest open offer on the collection
+    pub best_offer: Option<u128>,
+}
+
+// Running totals kept up to date as offers are made, taken and settled, amounts in the lend denom
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Default)]
+pub struct LoanStats {
+    pub open_offers: u64,
+    pub active_loans: u64,
+    // Funds waiting in open offers
+    pub tvl: u128,
+    pub outstanding_principal: u128,
+    // Principal of every loan taken so far
+    pub volume: u128,
+    // Lender share of the interest repaid
+    pub interest_earned: u128,
+    // Protocol share of the interest repaid
+    pub fees_collected: u128,
+    // Loans defaulted on or liquidated
+    pub defaults: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct UserStatsResp {
+    pub address: Addr,
+    // Offers made by the address, loans count for their original lender
+    pub as_lender: LoanStats,
+    // Loans taken by the address, offer counters stay at zero
+    pub as_borrower: LoanStats,
 }
 
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
 pub struct OfferListResp {
    pub offers: Vec<OfferResp>,
+   // Pass as `start_after` to fetch the next page, none on the last page
+   pub next_start_after: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct OffersByPriceResp {
+   pub offers: Vec<OfferResp>,
+   pub next_start_after: Option<PriceCursor>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct OrderBookDepthResp {
+   pub collection_id: u16,
+   pub levels: Vec<DepthLevel>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct DepthLevel {
+    pub amount: u128,
+    pub offers: u64,
+    // liquidity at this amount, `amount * offers`
+    pub total: u128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct HistoryResp {
+   pub offers: Vec<ArchivedOfferResp>,
+   pub next_start_after: Option<u64>,
+}
+
+// A closed offer or loan, moved out of `offer_resps()` when it reached its final status
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct ArchivedOfferResp {
+    // the offer as it was when closed
+    pub offer: OfferResp,
+    pub outcome: OfferStatus,
+    pub closed_at: u64,
+    // Paid by the borrower to close the loan, principal and interest
+    pub amount_paid: u128,
+    // Share of the interest kept as protocol fees
+    pub fees: u128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct PositionStatusResp {
+    pub offer_id: u64,
+    // The loan is active, false once it closed and its note and receipt are void
+    pub live: bool,
+    // How the loan closed
+    pub outcome: Option<OfferStatus>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct RepayQuoteResp {
+    pub loan_id: u64,
+    pub principal: u128,
+    pub interest: u128,
+    // Share of the interest kept as protocol fees
+    pub protocol_fee: u128,
+    // Always zero, loans past their due date default instead of accruing a late fee
+    pub late_fee: u128,
+    // Minimum payment `Repay` accepts, the excess is refunded
+    pub total_due: u128,
+    pub due_date: u64,
+    // Past the due date the loan can only default or be liquidated
+    pub overdue: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct SimulateBorrowResp {
+    // Error the borrow would fail with, none when it goes through
+    pub error: Option<String>,
+    // Paid out to the borrower
+    pub payout: u128,
+    pub appraised_value: u128,
+    pub loan_terms: Option<LoanTerms>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct SimulateLendResp {
+    // Error the lend would fail with, none when it goes through
+    pub error: Option<String>,
+    // Id the new offer would get
+    pub offer_id: u64,
+    // Most the offer may lend, none for bundle offers which are only capped by the
+    // appraisal of the bundle on borrow
+    pub max_amount: Option<u128>,
+}
+
+// Position in the price ordered offer list
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct PriceCursor {
+    pub amount: u128,
+    pub offer_id: u64,
+}
+
+// Position in the due date ordered loan list
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct DueCursor {
+    pub due_date: u64,
+    pub loan_id: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct LoansDueResp {
+    pub loans: Vec<OfferResp>,
+    pub next_start_after: Option<DueCursor>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
 pub struct OfferResp {
-    pub offer_id: u16,
+    pub offer_id: u64,
     pub owner: Addr,
     pub amount: u128,
     pub start_time: u64,
     pub collection_id: u16,
     pub token_id: String,
-    pub accepted: bool, 
-    pub borrower: Addr,
+    pub status: OfferStatus,
+    // Set once the offer is taken
+    pub borrower: Option<Addr>,
+    pub trait_filter: Option<Vec<TraitFilter>>,
+    pub bundle: bool,
+    pub collateral: Vec<Collateral>,
+    // Private offers can only be accepted by these addresses
+    pub allowed_borrowers: Option<Vec<Addr>>,
+    // cw721 contract holding the promissory note (token id = offer id) of an active loan.
+    // The contract can't burn notes, a note outlives its loan and is void once
+    // `PositionStatus` reports it closed, its holder may burn it.
+    pub note_contract: Option<Addr>,
+    // cw721 contract holding the borrower receipt (token id = offer id) of an active loan.
+    // Like notes, receipts can't be burned by the contract and are void once the loan closed.
+    pub receipt_contract: Option<Addr>,
+    // Where a third-party repayment may send the collateral besides the borrower
+    pub repay_recipient: Option<RepayRecipient>,
+    // Collection terms the loan was taken under, later collection updates don't apply to it
+    pub loan_terms: Option<LoanTerms>,
+}
+
+impl OfferResp {
+    // When an active loan with recorded terms falls due
+    pub fn due_date(&self) -> Option<u64> {
+        match (self.status, &self.loan_terms) {
+            (OfferStatus::Active, Some(terms)) => Some(self.start_time + terms.max_time),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct LoanTerms {
+    pub apy: u16,
+    pub max_time: u64,
+}
+
+// Where an offer is in its lifecycle, see `exec::transition` for the allowed moves
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy)]
+pub enum OfferStatus {
+    // Waiting for a borrower
+    Open,
+    // Taken as a loan, collateral in escrow
+    Active,
+    // Paid back, collateral returned
+    Repaid,
+    // The borrower settled past the due date by handing over the collateral
+    Defaulted,
+    // The lender claimed the collateral past the due date
+    Liquidated,
+    // Withdrawn by the lender or a pauser
+    Cancelled,
+    // Refunded because its collection was delisted
+    Expired,
+}
+
+impl OfferStatus {
+    // index key of the status
+    pub fn key(&self) -> &'static str {
+        match self {
+            OfferStatus::Open => "open",
+            OfferStatus::Active => "active",
+            OfferStatus::Repaid => "repaid",
+            OfferStatus::Defaulted => "defaulted",
+            OfferStatus::Liquidated => "liquidated",
+            OfferStatus::Cancelled => "cancelled",
+            OfferStatus::Expired => "expired",
+        }
+    }
+
+    // No further transition out of closed statuses
+    pub fn is_closed(&self) -> bool {
+        !matches!(self, OfferStatus::Open | OfferStatus::Active)
+    }
 }
 
 pub struct OfferRespIndexes<'a> {
-    pub id: UniqueIndex<'a, u16, OfferResp, (&'a Addr, u16)>,
-    // pub borrow: MultiIndex<'a, u16, OfferResp, (&'a Addr, u16)>,
+    // the lender
+    pub owner: MultiIndex<'a, Addr, OfferResp, u64>,
+    pub borrower: MultiIndex<'a, Addr, OfferResp, u64>,
+    pub status: MultiIndex<'a, String, OfferResp, u64>,
+    pub collection: MultiIndex<'a, u16, OfferResp, u64>,
+    pub price: MultiIndex<'a, u128, OfferResp, u64>,
+    // (collection, status, amount), the order book of each collection is its open prefix
+    pub book: MultiIndex<'a, (u16, String, u128), OfferResp, u64>,
+    // (collection, token, amount), bids reserved for each token
+    pub token: MultiIndex<'a, (u16, String, u128), OfferResp, u64>,
+    // due date of active loans
+    pub due: MultiIndex<'a, u64, OfferResp, u64>,
 }
 
 impl IndexList<OfferResp> for OfferRespIndexes<'_> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<OfferResp>> + '_> {
         let v: Vec<&dyn Index<OfferResp>> =
-            vec![&self.id/*, &self.borrow*/];
+            vec![&self.owner, &self.borrower, &self.status, &self.collection, &self.price, &self.book, &self.token, &self.due];
         Box::new(v.into_iter())
     }
 }
 
-// offer_resps() is the storage access function.
-pub fn offer_resps<'a>() -> IndexedMap<'a,(&'a Addr, u16), OfferResp, OfferRespIndexes<'a>> {
+// offer_resps() is the storage access function, offers are keyed by their global id.
+// The `offers_im` layout keyed by (owner, u16 id) is rewritten by `upgrades` on migrate.
+pub fn offer_resps<'a>() -> IndexedMap<'a, u64, OfferResp, OfferRespIndexes<'a>> {
     let indexes = OfferRespIndexes {
-      id: UniqueIndex::new(|a_offer| a_offer.offer_id, "offer__id"),
-    //   borrow: MultiIndex::new(
-    //     |_pk, a_offer| a_offer.offer_id,
-    //     "offers_im",
-    //     "offers_borrow",
-    //   )
+      owner: MultiIndex::new(
+        |_pk, a_offer| a_offer.owner.clone(),
+        "offers",
+        "offers__owner",
+      ),
+      // open offers are all kept under the empty address
+      borrower: MultiIndex::new(
+        |_pk, a_offer| a_offer.borrower.clone().unwrap_or_else(|| Addr::unchecked("")),
+        "offers",
+        "offers__borrower",
+      ),
+      status: MultiIndex::new(
+        |_pk, a_offer| a_offer.status.key().to_string(),
+        "offers",
+        "offers__status",
+      ),
+      collection: MultiIndex::new(
+        |_pk, a_offer| a_offer.collection_id,
+        "offers",
+        "offers__collection",
+      ),
+      price: MultiIndex::new(
+        |_pk, a_offer| a_offer.amount,
+        "offers",
+        "offers__price",
+      ),
+      book: MultiIndex::new(
+        |_pk, a_offer| (a_offer.collection_id, a_offer.status.key().to_string(), a_offer.amount),
+        "offers",
+        "offers__book",
+      ),
+      token: MultiIndex::new(
+        |_pk, a_offer| (a_offer.collection_id, a_offer.token_id.clone(), a_offer.amount),
+        "offers",
+        "offers__token",
+      ),
+      // open offers are never due and sort last
+      due: MultiIndex::new(
+        |_pk, a_offer| a_offer.due_date().unwrap_or(u64::MAX),
+        "offers",
+        "offers__due",
+      ),
+    };
+    IndexedMap::new("offers", indexes)
+}
+
+pub struct ArchivedOfferIndexes<'a> {
+    pub lender: MultiIndex<'a, Addr, ArchivedOfferResp, u64>,
+    pub borrower: MultiIndex<'a, Addr, ArchivedOfferResp, u64>,
+    pub collection: MultiIndex<'a, u16, ArchivedOfferResp, u64>,
+    pub outcome: MultiIndex<'a, String, ArchivedOfferResp, u64>,
+}
+
+impl IndexList<ArchivedOfferResp> for ArchivedOfferIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ArchivedOfferResp>> + '_> {
+        let v: Vec<&dyn Index<ArchivedOfferResp>> = vec![&self.lender, &self.borrower, &self.collection, &self.outcome];
+        Box::new(v.into_iter())
+    }
+}
+
+// archived_offers() holds closed offers under their offer id
+pub fn archived_offers<'a>() -> IndexedMap<'a, u64, ArchivedOfferResp, ArchivedOfferIndexes<'a>> {
+    let indexes = ArchivedOfferIndexes {
+      lender: MultiIndex::new(
+        |_pk, archived| archived.offer.owner.clone(),
+        "archive",
+        "archive__lender",
+      ),
+      // offers closed before being taken are kept under the empty address
+      borrower: MultiIndex::new(
+        |_pk, archived| archived.offer.borrower.clone().unwrap_or_else(|| Addr::unchecked("")),
+        "archive",
+        "archive__borrower",
+      ),
+      collection: MultiIndex::new(
+        |_pk, archived| archived.offer.collection_id,
+        "archive",
+        "archive__collection",
+      ),
+      outcome: MultiIndex::new(
+        |_pk, archived| archived.outcome.key().to_string(),
+        "archive",
+        "archive__outcome",
+      ),
     };
-    IndexedMap::new("offers_im", indexes)
+    IndexedMap::new("archive", indexes)
+}
+
+// Collection as listed by a collection manager, the id is assigned by the contract
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct NFTCollectionMsg {
+    pub collection: String,
+    pub floor_price: u128,
+    pub contract: Addr,
+    pub apy: u16,
+    pub max_time: u64,
+    #[serde(default)]
+    pub risk_tier: u8,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
 pub struct NFTCollectionResp {
     pub collection_id: u16,
     pub collection: String,
@@ -93,10 +510,176 @@ pub struct NFTCollectionResp {
     pub contract: Addr,
     pub apy: u16,
     pub max_time: u64,
+    // Risk rating set by the collection managers, 0 when unrated
+    #[serde(default)]
+    pub risk_tier: u8,
+    // Disabled collections take no new offers or loans, existing loans still settle
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+// Permissions the owner (`ContractConfig.admin`) can delegate, the owner holds all of them
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy)]
+pub enum Role {
+    CollectionManager,
+    PriceUpdater,
+    Pauser,
+    FeeCollector,
+    // Sets token and trait appraisals
+    Appraiser,
+}
+
+impl Role {
+    pub const ALL: [Role; 5] = [Role::CollectionManager, Role::PriceUpdater, Role::Pauser, Role::FeeCollector, Role::Appraiser];
+
+    // storage key of the role
+    pub fn key(&self) -> &'static str {
+        match self {
+            Role::CollectionManager => "collection_manager",
+            Role::PriceUpdater => "price_updater",
+            Role::Pauser => "pauser",
+            Role::FeeCollector => "fee_collector",
+            Role::Appraiser => "appraiser",
+        }
+    }
+}
+
+// Operations that can be paused, globally or for a collection
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy)]
+pub enum Operation {
+    Lend,
+    Borrow,
+    Repay,
+    // Claiming the collateral of an overdue loan
+    Liquidate,
+    Cancel,
+    // Collection, pricing, appraisal and fee management
+    Admin,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 6] = [
+        Operation::Lend,
+        Operation::Borrow,
+        Operation::Repay,
+        Operation::Liquidate,
+        Operation::Cancel,
+        Operation::Admin,
+    ];
+
+    // storage key of the operation
+    pub fn key(&self) -> &'static str {
+        match self {
+            Operation::Lend => "lend",
+            Operation::Borrow => "borrow",
+            Operation::Repay => "repay",
+            Operation::Liquidate => "liquidate",
+            Operation::Cancel => "cancel",
+            Operation::Admin => "admin",
+        }
+    }
+}
+
+// Exits that ignore pauses so users can always get their funds and NFTs back
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct PauseSettings {
+    pub repay_open_during_pause: bool,
+    pub cancel_open_during_pause: bool,
+}
+
+impl Default for PauseSettings {
+    fn default() -> Self {
+        PauseSettings { repay_open_during_pause: true, cancel_open_during_pause: true }
+    }
+}
+
+impl PauseSettings {
+    // Whether `operation` ignores pauses, such operations can't be paused
+    pub fn stays_open(&self, operation: Operation) -> bool {
+        match operation {
+            Operation::Repay => self.repay_open_during_pause,
+            Operation::Cancel => self.cancel_open_during_pause,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct CollectionPauseResp {
+    pub collection_id: u16,
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct PauseStateResp {
+    pub global: Vec<Operation>,
+    pub collections: Vec<CollectionPauseResp>,
+    pub settings: PauseSettings,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
 pub struct ContractConfig {
     pub admin: Addr,
     pub interest: u128,
 }
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct ConfigResp {
+    pub admin: Addr,
+    pub pending_admin: Option<Addr>,
+    // Lender share of the interest in percent, the rest is protocol fees
+    pub interest: u128,
+    pub accrued_fees: u128,
+    pub denom: String,
+    // Last ids handed out, ids are never reused
+    pub last_offer_id: u64,
+    pub last_collection_id: u16,
+    pub note_contract: Option<Addr>,
+    pub receipt_contract: Option<Addr>,
+    pub pause: PauseStateResp,
+}
+
+// Appraised value of a single token: floor_price * multiplier / 100
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct AppraisalResp {
+    pub collection_id: u16,
+    pub token_id: String,
+    pub floor_price: u128,
+    pub multiplier: u16,
+    pub value: u128,
+}
+
+// A single attribute of the cw721 `Metadata` extension
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct Trait {
+    pub display_type: Option<String>,
+    pub trait_type: String,
+    pub value: String,
+}
+
+// Recipient of the collateral authorized by the borrower for third-party repayments
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct RepayRecipient {
+    pub authorized_by: Addr,
+    pub recipient: Addr,
+}
+
+// One NFT held in escrow for a loan
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct Collateral {
+    pub collection_id: u16,
+    pub token_id: String,
+}
+
+// Trait an NFT must carry to be accepted by an offer, e.g. "Background: Gold"
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct TraitFilter {
+    pub trait_type: String,
+    pub value: String,
+}
+
+// Subset of the cw721 `Metadata` extension that the appraisal reads
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Default)]
+pub struct NftMetadata {
+    pub attributes: Option<Vec<Trait>>,
+}
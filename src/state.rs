@@ -1,9 +1,41 @@
+use cosmwasm_std::Addr;
 use cw_storage_plus::{ Item, Map };
 
-use crate::msg::{ NFTCollectionResp, ContractConfig };
+use crate::msg::{ NFTCollectionResp, LoanStats, ContractConfig, PauseSettings };
 
 // pub const NFT_COLLECTIONS: Item<Vec<NFTCollectionResp>> = Item::new("nft_collections");
 pub const NFT_COLLECTIONS: Map<u16, NFTCollectionResp> = Map::new("nft_collections");
-pub const LAST_OFFER_INDEX: Item<u16> = Item::new("0");
+// Id of the last listed collection, ids are never reused
+pub const LAST_COLLECTION_ID: Item<u16> = Item::new("last_collection_id");
+// NFT contract -> id of the collection listing it
+pub const COLLECTION_BY_CONTRACT: Map<&Addr, u16> = Map::new("collection_by_contract");
+// Offer and loan totals of the whole protocol, per collection id and per address
+pub const STATS: Item<LoanStats> = Item::new("stats");
+pub const COLLECTION_STATS: Map<u16, LoanStats> = Map::new("collection_stats");
+// Totals of the offers an address made and of the loans it took
+pub const LENDER_STATS: Map<&Addr, LoanStats> = Map::new("lender_stats");
+pub const BORROWER_STATS: Map<&Addr, LoanStats> = Map::new("borrower_stats");
+// Active loans made on a collection or holding one of its NFTs, by collection id
+pub const COLLECTION_LOANS: Map<u16, u64> = Map::new("collection_loans");
+pub const LAST_OFFER_INDEX: Item<u64> = Item::new("0");
 pub const LEND_DENOM: Item<String> = Item::new("SEI");
-pub const CONFIG: Item<ContractConfig> = Item::new("config");
\ No newline at end of file
+pub const CONFIG: Item<ContractConfig> = Item::new("config");
+// (collection_id, token_id) -> multiplier in percent of floor price
+pub const TOKEN_APPRAISALS: Map<(u16, &str), u16> = Map::new("token_appraisals");
+// (collection_id, trait_type, value) -> multiplier in percent of floor price
+pub const TRAIT_APPRAISALS: Map<(u16, &str, &str), u16> = Map::new("trait_appraisals");
+// Companion cw721 contract minting promissory notes for the lender side of loans
+pub const NOTE_CONTRACT: Item<Addr> = Item::new("note_contract");
+// Companion cw721 contract minting receipts for the borrower side of loans
+pub const RECEIPT_CONTRACT: Item<Addr> = Item::new("receipt_contract");
+// Admin proposed by the current one, takes over once it accepts
+pub const PENDING_ADMIN: Item<Addr> = Item::new("pending_admin");
+// Delegated roles keyed by (role key, address)
+pub const ROLES: Map<(&str, &Addr), bool> = Map::new("roles");
+// Protocol share of the interest waiting to be withdrawn by a fee collector
+pub const ACCRUED_FEES: Item<u128> = Item::new("accrued_fees");
+// Operation keys paused for every collection
+pub const PAUSED_OPERATIONS: Map<&str, bool> = Map::new("paused_operations");
+// (collection_id, operation key) paused for a single collection
+pub const PAUSED_COLLECTION_OPERATIONS: Map<(u16, &str), bool> = Map::new("paused_collection_operations");
+pub const PAUSE_SETTINGS: Item<PauseSettings> = Item::new("pause_settings");
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, Order, StdResult, Storage};
+use cw_storage_plus::{Index, IndexList, IndexedMap, UniqueIndex};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+use crate::msg::{offer_resps, LoanStats, Collateral, LoanTerms, OfferResp, OfferStatus};
+use crate::state::{BORROWER_STATS, COLLECTION_BY_CONTRACT, COLLECTION_LOANS, COLLECTION_STATS, LAST_COLLECTION_ID, LENDER_STATS, NFT_COLLECTIONS, STATS};
+
+// Deployments without cw2 version info run the first release
+pub const FIRST_RELEASE: &str = "0.1.0";
+
+type Upgrade = fn(&mut dyn Storage) -> StdResult<()>;
+
+// State upgrades in release order, keyed by the version introducing them
+const UPGRADES: &[(&str, Upgrade)] = &[
+    ("0.2.0", upgrade_0_2_0),
+];
+
+// Runs every upgrade released after `from` up to `to`, returns the versions applied
+pub fn run_upgrades(storage: &mut dyn Storage, from: &Version, to: &Version) -> Result<Vec<&'static str>, ContractError> {
+    let mut applied = vec![];
+    for (version, upgrade) in UPGRADES {
+        let release: Version = version.parse()?;
+        if *from < release && release <= *to {
+            upgrade(storage)?;
+            applied.push(*version);
+        }
+    }
+    Ok(applied)
+}
+
+// 0.2.0: offers keyed by global u64 ids, contract assigned collection ids, loan term
+// snapshots, running stats and active loans counted per collection
+fn upgrade_0_2_0(storage: &mut dyn Storage) -> StdResult<()> {
+    migrate_offers_to_global_ids(storage)?;
+    index_collections(storage)?;
+    snapshot_loan_terms(storage)?;
+    rebuild_stats(storage)?;
+    count_collection_loans(storage)
+}
+
+// Offer as the first release stored it under `offers_im`, keyed by (owner, u16 id)
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct LegacyOfferResp {
+    pub offer_id: u16,
+    pub owner: Addr,
+    pub amount: u128,
+    pub start_time: u64,
+    pub collection_id: u16,
+    pub token_id: String,
+    pub accepted: bool,
+    pub borrower: Addr,
+}
+
+pub struct LegacyOfferRespIndexes<'a> {
+    pub id: UniqueIndex<'a, u16, LegacyOfferResp, (&'a Addr, u16)>,
+}
+
+impl IndexList<LegacyOfferResp> for LegacyOfferRespIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<LegacyOfferResp>> + '_> {
+        let v: Vec<&dyn Index<LegacyOfferResp>> = vec![&self.id];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn legacy_offer_resps<'a>() -> IndexedMap<'a, (&'a Addr, u16), LegacyOfferResp, LegacyOfferRespIndexes<'a>> {
+    let indexes = LegacyOfferRespIndexes {
+        id: UniqueIndex::new(|a_offer| a_offer.offer_id, "offer__id"),
+    };
+    IndexedMap::new("offers_im", indexes)
+}
+
+impl From<LegacyOfferResp> for OfferResp {
+    fn from(offer: LegacyOfferResp) -> Self {
+        // Accepted offers escrow the token recorded in `token_id`, open ones have the placeholder borrower `none`
+        let (status, borrower, collateral) = if offer.accepted {
+            let collateral = vec![Collateral { collection_id: offer.collection_id, token_id: offer.token_id.clone() }];
+            (OfferStatus::Active, Some(offer.borrower), collateral)
+        } else {
+            (OfferStatus::Open, None, vec![])
+        };
+
+        OfferResp {
+            offer_id: offer.offer_id.into(),
+            owner: offer.owner,
+            amount: offer.amount,
+            start_time: offer.start_time,
+            collection_id: offer.collection_id,
+            token_id: offer.token_id,
+            status,
+            borrower,
+            trait_filter: None,
+            bundle: false,
+            collateral,
+            allowed_borrowers: None,
+            note_contract: None,
+            receipt_contract: None,
+            repay_recipient: None,
+            loan_terms: None,
+        }
+    }
+}
+
+// Collections listed by the first release keep their ids, new ones continue after the last
+fn index_collections(storage: &mut dyn Storage) -> StdResult<()> {
+    let collections = NFT_COLLECTIONS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut last_collection_id = 0;
+    for (collection_id, collection) in collections {
+        COLLECTION_BY_CONTRACT.save(storage, &collection.contract, &collection_id)?;
+        last_collection_id = collection_id;
+    }
+    LAST_COLLECTION_ID.save(storage, &last_collection_id)
+}
+
+// Active loans get their collection's current terms, which they followed until now
+fn snapshot_loan_terms(storage: &mut dyn Storage) -> StdResult<()> {
+    for (_id, loan) in active_loans(storage)? {
+        let Some(collection) = NFT_COLLECTIONS.may_load(storage, loan.collection_id)? else {
+            continue;
+        };
+        let loan_terms = LoanTerms { apy: collection.apy, max_time: collection.max_time };
+        offer_resps().replace(storage, loan.offer_id, Some(&OfferResp { loan_terms: Some(loan_terms), ..loan.clone() }), Some(&loan))?;
+    }
+    Ok(())
+}
+
+fn active_loans(storage: &dyn Storage) -> StdResult<Vec<(u64, OfferResp)>> {
+    offer_resps()
+        .idx
+        .status
+        .prefix(OfferStatus::Active.key().to_string())
+        .range(storage, None, None, Order::Ascending)
+        .collect()
+}
+
+// Running totals of the open offers and active loans. Interest and fees of loans
+// repaid before stats were kept are unknown and not counted.
+fn rebuild_stats(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut totals = StatsTotals::default();
+    for entry in offer_resps().range(storage, None, None, Order::Ascending) {
+        let (_id, offer) = entry?;
+        match offer.status {
+            OfferStatus::Open => totals.add(&offer, |stats| {
+                stats.open_offers += 1;
+                stats.tvl += offer.amount;
+            }),
+            OfferStatus::Active => totals.add(&offer, |stats| {
+                stats.active_loans += 1;
+                stats.outstanding_principal += offer.amount;
+                stats.volume += offer.amount;
+            }),
+            _ => {},
+        }
+    }
+
+    STATS.save(storage, &totals.protocol)?;
+    for (collection_id, stats) in totals.collections {
+        COLLECTION_STATS.save(storage, collection_id, &stats)?;
+    }
+    for (lender, stats) in totals.lenders {
+        LENDER_STATS.save(storage, &lender, &stats)?;
+    }
+    for (borrower, stats) in totals.borrowers {
+        BORROWER_STATS.save(storage, &borrower, &stats)?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct StatsTotals {
+    protocol: LoanStats,
+    collections: BTreeMap<u16, LoanStats>,
+    lenders: BTreeMap<Addr, LoanStats>,
+    borrowers: BTreeMap<Addr, LoanStats>,
+}
+
+impl StatsTotals {
+    // Same bookkeeping as the contract: protocol, collection and lender, plus the borrower of loans
+    fn add(&mut self, offer: &OfferResp, update: impl Fn(&mut LoanStats)) {
+        update(&mut self.protocol);
+        update(self.collections.entry(offer.collection_id).or_default());
+        update(self.lenders.entry(offer.owner.clone()).or_default());
+        if let Some(borrower) = &offer.borrower {
+            update(self.borrowers.entry(borrower.clone()).or_default());
+        }
+    }
+}
+
+// Active loans counted per collection they were made on or hold NFTs of
+fn count_collection_loans(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut counts: BTreeMap<u16, u64> = BTreeMap::new();
+    for (_id, loan) in active_loans(storage)? {
+        let mut collection_ids: Vec<u16> = loan.collateral.iter().map(|item| item.collection_id).collect();
+        collection_ids.push(loan.collection_id);
+        collection_ids.sort_unstable();
+        collection_ids.dedup();
+        for collection_id in collection_ids {
+            *counts.entry(collection_id).or_default() += 1;
+        }
+    }
+
+    for (collection_id, count) in counts {
+        COLLECTION_LOANS.save(storage, collection_id, &count)?;
+    }
+    Ok(())
+}
+
+// Moves every offer from `offers_im` to `offers`, keeping its id. Returns the number of offers moved.
+pub fn migrate_offers_to_global_ids(storage: &mut dyn Storage) -> StdResult<u64> {
+    let legacy_offers = legacy_offer_resps()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for ((owner, offer_id), offer) in &legacy_offers {
+        legacy_offer_resps().remove(storage, (owner, *offer_id))?;
+        offer_resps().save(storage, (*offer_id).into(), &offer.clone().into())?;
+    }
+
+    Ok(legacy_offers.len() as u64)
+}